@@ -1,8 +1,5 @@
 use asciigame::{*};
 
-use winit::event_loop::EventLoop;
-use anyhow::Result;
-
 struct Walker {
   should_run: bool,
 }
@@ -43,23 +40,9 @@ fn startup_win(c: &mut criterion::Criterion) {
   c.bench_function("window_game_startup", |b| {
       b.iter(|| {
       std::hint::black_box({
-        let mut game = WindowGame::<Walker>::new();
-        if let Ok(mut g) = game {
-          let event_loop = EventLoop::new()?;
-          if let Err(e) = event_loop.run_app(&mut g) {
-            game = Err(e.into());
-          } else {
-            game = Ok(g);
-          }
-        }
-        
-        // 4. Report
-        if let Err(e) = game {
-          eprintln!("Game Error: {:?}", e);
-          return Err(e);
-        }
+        WindowGame::<Walker>::new()?.run()?;
         
-        Ok(())
+        anyhow::Ok(())
       })
     })
   });
@@ -69,22 +52,9 @@ fn startup_term(c: &mut criterion::Criterion) {
   c.bench_function("terminal_game_startup", |b| {
       b.iter({
       std::hint::black_box(|| {
-        let mut game = TerminalGame::<Walker>::new();
-        if let Ok(mut g) = game {
-          if let Err(e) = g.run() {
-            game = Err(e);
-          } else {
-            game = Ok(g);
-          }
-        }
-        
-        // 4. Report
-        if let Err(e) = game {
-          eprintln!("Game Error: {:?}", e);
-          return Err(e);
-        }
+        TerminalGame::<Walker>::new()?.run()?;
         
-        Ok(())
+        anyhow::Ok(())
       })
     })
   });
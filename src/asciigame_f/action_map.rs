@@ -0,0 +1,291 @@
+use crate::{KeyChord, encode_key, decode_key, encode_button, decode_button};
+
+use crossterm::event::{KeyCode, KeyModifiers, MouseButton};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use anyhow::{Result, anyhow, bail};
+
+// Keymap files are plain text, one action per line followed by all of its inputs,
+// e.g. "move_up = w Up Ctrl+k Keypad+8 F1 Mouse:Left". Printable characters are written as themselves,
+// everything else is named like in replay files ("Char:32" for space, "Char:43" for '+', which separates modifiers).
+// Lines starting with '#' are comments.
+
+const MODIFIER_NAMES: [(KeyModifiers, &str); 4] = [
+  (KeyModifiers::CONTROL, "Ctrl"),
+  (KeyModifiers::ALT, "Alt"),
+  (KeyModifiers::SHIFT, "Shift"),
+  (KeyModifiers::SUPER, "Super"),
+];
+
+/// Something the player can press to trigger an action.
+#[derive(Eq, PartialEq, Hash, Clone, Copy, Debug)]
+pub enum Input {
+  Key(KeyChord),
+  Mouse(MouseButton),
+}
+
+impl From<KeyChord> for Input {
+  fn from(chord: KeyChord) -> Self {
+    Input::Key(chord)
+  }
+}
+
+impl From<KeyCode> for Input {
+  fn from(code: KeyCode) -> Self {
+    Input::Key(code.into())
+  }
+}
+
+impl From<MouseButton> for Input {
+  fn from(button: MouseButton) -> Self {
+    Input::Mouse(button)
+  }
+}
+
+impl Input {
+  /// Turns the input into a single whitespace-free word.
+  pub fn encode(&self) -> String {
+    match self {
+      Input::Mouse(button) => format!("Mouse:{}", encode_button(*button)),
+      Input::Key(chord) => {
+        let mut word = String::new();
+
+        for (modifier, name) in MODIFIER_NAMES {
+          if chord.modifiers.contains(modifier) {
+            word.push_str(name);
+            word.push('+');
+          }
+        }
+        if chord.keypad {
+          word.push_str("Keypad+");
+        }
+        word.push_str(&encode_keymap_key(chord.code));
+
+        word
+      },
+    }
+  }
+
+  pub fn decode(word: &str) -> Option<Self> {
+    if let Some(button) = word.strip_prefix("Mouse:") {
+      return Some(Input::Mouse(decode_button(button)?));
+    }
+
+    let mut parts: Vec<&str> = word.split('+').collect();
+    let code = decode_keymap_key(parts.pop()?)?;
+
+    let mut modifiers = KeyModifiers::empty();
+    let mut keypad = false;
+    for part in parts {
+      if part == "Keypad" {
+        keypad = true;
+        continue;
+      }
+
+      let (modifier, _) = MODIFIER_NAMES.iter().find(|(_, name)| *name == part)?;
+      modifiers |= *modifier;
+    }
+
+    Some(Input::Key(KeyChord { keypad, ..KeyChord::new(code, modifiers) }))
+  }
+}
+
+fn encode_keymap_key(code: KeyCode) -> String {
+  match code {
+    KeyCode::Char(c) if is_written_as_is(c) => c.to_string(),
+    KeyCode::F(n) => format!("F{}", n),
+    _ => encode_key(code),
+  }
+}
+
+fn decode_keymap_key(word: &str) -> Option<KeyCode> {
+  let mut chars = word.chars();
+
+  if let (Some(c), None) = (chars.next(), chars.next()) && is_written_as_is(c) {
+    return Some(KeyCode::Char(c));
+  }
+  if let Some(n) = word.strip_prefix('F') && let Ok(n) = n.parse() {
+    return Some(KeyCode::F(n));
+  }
+
+  decode_key(word)
+}
+
+fn is_written_as_is(c: char) -> bool {
+  !c.is_whitespace() && !c.is_control() && c != '+'
+}
+
+/// Named actions ("move_up", "quit", ...), each mapped to any number of inputs.
+/// The game binds and queries actions by name, so the player can remap them without the game knowing.
+#[derive(Default, Clone)]
+pub struct ActionMap {
+  actions: BTreeMap<String, Vec<Input>>, // sorted, so saved keymaps stay diffable
+}
+
+impl ActionMap {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Adds an input to an action, creating the action if needed.
+  pub fn bind<I>(&mut self, action: &str, input: I) -> &mut Self
+  where I: Into<Input> {
+    let input = input.into();
+    let inputs = self.actions.entry(action.to_string()).or_default();
+
+    if !inputs.contains(&input) {
+      inputs.push(input);
+    }
+
+    self
+  }
+
+  /// Replaces all of an action's inputs.
+  pub fn rebind<I>(&mut self, action: &str, inputs: impl IntoIterator<Item = I>) -> &mut Self
+  where I: Into<Input> {
+    self.actions.insert(action.to_string(), inputs.into_iter().map(Into::into).collect());
+
+    self
+  }
+
+  pub fn unbind<I>(&mut self, action: &str, input: I) -> &mut Self
+  where I: Into<Input> {
+    let input = input.into();
+
+    if let Some(inputs) = self.actions.get_mut(action) {
+      inputs.retain(|bound| *bound != input);
+    }
+
+    self
+  }
+
+  /// Removes every input of an action. The action itself stays, so it's still saved to the keymap.
+  pub fn clear(&mut self, action: &str) -> &mut Self {
+    if let Some(inputs) = self.actions.get_mut(action) {
+      inputs.clear();
+    }
+
+    self
+  }
+
+  pub fn inputs(&self, action: &str) -> &[Input] {
+    self.actions.get(action).map_or(&[], |inputs| inputs.as_slice())
+  }
+
+  pub fn actions(&self) -> impl Iterator<Item = &str> {
+    self.actions.keys().map(|name| name.as_str())
+  }
+
+  /// Reads a keymap file. Every action in the file replaces the one bound here, the others are left alone,
+  /// so the game can bind its defaults first and load the player's keymap over them.
+  pub fn load(&mut self, path: &Path) -> Result<()> {
+    let reader = BufReader::new(File::open(path)?);
+
+    for (line_index, line) in reader.lines().enumerate() {
+      let line = line?;
+      let line = line.trim();
+
+      if line.is_empty() || line.starts_with('#') { continue; }
+
+      let (name, words) = line.split_once('=').ok_or_else(|| anyhow!("line {}: missing '='", line_index + 1))?;
+
+      let mut inputs = Vec::new();
+      for word in words.split_whitespace() {
+        let Some(input) = Input::decode(word) else {
+          bail!("line {}: bad input '{}'", line_index + 1, word);
+        };
+        inputs.push(input);
+      }
+
+      self.actions.insert(name.trim().to_string(), inputs);
+    }
+
+    Ok(())
+  }
+
+  pub fn save(&self, path: &Path) -> Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+
+    for (name, inputs) in &self.actions {
+      write!(writer, "{} =", name)?;
+      for input in inputs {
+        write!(writer, " {}", input.encode())?;
+      }
+      writeln!(writer)?;
+    }
+    writer.flush()?;
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crossterm::event::MediaKeyCode;
+
+  fn chord(code: KeyCode, modifiers: KeyModifiers) -> Input {
+    Input::Key(KeyChord::new(code, modifiers))
+  }
+
+  #[test]
+  fn inputs_get_readable_names() {
+    let cases = [
+      (chord(KeyCode::Char('w'), KeyModifiers::NONE), "w"),
+      (chord(KeyCode::Up, KeyModifiers::NONE), "Up"),
+      (chord(KeyCode::Char('s'), KeyModifiers::CONTROL), "Ctrl+s"),
+      (Input::Key(KeyChord { keypad: true, ..KeyChord::new(KeyCode::Char('8'), KeyModifiers::NONE) }), "Keypad+8"),
+      (chord(KeyCode::F(1), KeyModifiers::ALT), "Alt+F1"),
+      (chord(KeyCode::Char(';'), KeyModifiers::NONE), ";"),
+      (chord(KeyCode::Char('#'), KeyModifiers::NONE), "Shift+3"),
+      (chord(KeyCode::Char('+'), KeyModifiers::NONE), "Shift+="),
+      (Input::Mouse(MouseButton::Left), "Mouse:Left"),
+      (chord(KeyCode::Media(MediaKeyCode::PlayPause), KeyModifiers::NONE), "Media:PlayPause"),
+      // characters that can't be written as themselves fall back to their number
+      (chord(KeyCode::Char(' '), KeyModifiers::NONE), "Char:32"),
+      (chord(KeyCode::Char('\t'), KeyModifiers::NONE), "Char:9"),
+    ];
+
+    for (input, word) in cases {
+      assert_eq!(input.encode(), word);
+      assert_eq!(Input::decode(word), Some(input), "decoding {}", word);
+    }
+  }
+
+  #[test]
+  fn the_numeric_form_still_loads() {
+    assert_eq!(Input::decode("Char:119"), Some(chord(KeyCode::Char('w'), KeyModifiers::NONE)));
+    assert_eq!(Input::decode("Ctrl+F:5"), Some(chord(KeyCode::F(5), KeyModifiers::CONTROL)));
+    assert_eq!(Input::decode("Hyper+w"), None);
+
+    // '+' separates the modifiers, so a chord that kept a raw '+' needs the number
+    let plus = Input::Key(KeyChord { code: KeyCode::Char('+'), modifiers: KeyModifiers::CONTROL, keypad: true });
+    assert_eq!(plus.encode(), "Ctrl+Keypad+Char:43");
+  }
+
+  #[test]
+  fn saved_keymaps_load_the_same_actions() {
+    let path = std::env::temp_dir().join(format!("asciigame_{}_keymap.txt", std::process::id()));
+
+    let mut actions = ActionMap::new();
+    actions
+      .rebind("move_up", [KeyCode::Char('w'), KeyCode::Up])
+      .bind("move_up", KeyChord::on_keypad(KeyCode::Char('8')))
+      .bind("pause", KeyCode::Media(MediaKeyCode::PlayPause))
+      .bind("pause", KeyChord::ctrl(KeyCode::Char(' ')))
+      .bind("shoot", MouseButton::Left)
+      .rebind::<Input>("unbound", []);
+    actions.save(&path).unwrap();
+
+    let mut loaded = ActionMap::new();
+    loaded.load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(loaded.actions().eq(actions.actions()));
+    for action in actions.actions() {
+      assert_eq!(loaded.inputs(action), actions.inputs(action), "{}", action);
+    }
+  }
+}
@@ -0,0 +1,42 @@
+use crate::{DrawBuffer, InputManager, KeyReleaseMode};
+
+use anyhow::Result;
+
+/// A platform the Engine can run on. It shows the DrawBuffer and feeds the InputManager,
+/// while the frame loop itself lives in the Engine, so every backend gets the same input semantics.
+pub trait Backend {
+  /// Called once, before the first frame.
+  fn init(&mut self) -> Result<()> {
+    Ok(())
+  }
+
+  /// Called once after the last frame, even if the game loop returned an error.
+  fn shutdown(&mut self) -> Result<()> {
+    Ok(())
+  }
+
+  /// Size of the screen, in cells.
+  fn size(&self) -> (usize, usize);
+
+  /// Moves every pending platform event into the InputManager.
+  fn pump_input(&mut self, inp_man: &mut InputManager) -> Result<()>;
+
+  /// Shows the DrawBuffer on screen, with its top-left cell placed at 'offset' (see 'Engine::screen_offset').
+  /// Backends that scale the DrawBuffer to fit may ignore the offset.
+  fn present(&mut self, db: &mut DrawBuffer, offset: (i32, i32)) -> Result<()>;
+
+  /// True when the platform itself asks the game to stop (e.g. the window was closed).
+  fn should_quit(&self) -> bool {
+    false
+  }
+
+  /// Whether the platform reports key releases. Asked once, after 'init'.
+  fn key_release_mode(&self) -> KeyReleaseMode {
+    KeyReleaseMode::Reported
+  }
+
+  /// Whether the Engine should sleep between frames to keep the framerate.
+  fn syncs_frames(&self) -> bool {
+    true
+  }
+}
@@ -0,0 +1,197 @@
+use crate::{DrawTarget, Rect, View};
+
+/// Maps world cells to DrawBuffer cells, so a world can be bigger than the screen.
+/// The world is drawn into 'viewport', with the world cell 'position' on its top-left corner.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Camera {
+  pub position: (i32, i32),
+  pub viewport: Rect, // in DrawBuffer cells
+  pub deadzone: (usize, usize), // box around the viewport's center that a followed target moves in without scrolling
+  pub bounds: Option<Rect>, // the world, the camera doesn't scroll past it
+}
+
+impl Camera {
+  pub fn new(viewport: Rect) -> Self {
+    Self {
+      viewport,
+      ..Default::default()
+    }
+  }
+
+  pub fn deadzone(mut self, width: usize, height: usize) -> Self {
+    self.deadzone = (width, height);
+
+    self
+  }
+
+  pub fn bounds(mut self, world: Rect) -> Self {
+    self.bounds = Some(world);
+
+    self
+  }
+
+  /// The DrawBuffer cell a world cell is drawn on. It may lie outside of the viewport.
+  pub fn world_to_screen(&self, (x, y): (i32, i32)) -> (i32, i32) {
+    (x - self.position.0 + self.viewport.x, y - self.position.1 + self.viewport.y)
+  }
+
+  /// The world cell under a DrawBuffer cell, e.g. the mouse position. None outside of the viewport.
+  pub fn screen_to_world(&self, (col, row): (i32, i32)) -> Option<(i32, i32)> {
+    if !self.viewport.contains(col, row) { return None; }
+
+    Some((col - self.viewport.x + self.position.0, row - self.viewport.y + self.position.1))
+  }
+
+  /// The part of the world inside the viewport.
+  pub fn visible(&self) -> Rect {
+    Rect::new(self.position.0, self.position.1, self.viewport.width, self.viewport.height)
+  }
+
+  pub fn is_visible(&self, (x, y): (i32, i32)) -> bool {
+    self.visible().contains(x, y)
+  }
+
+  /// A View on the viewport that takes world coordinates. Everything outside of the viewport is clipped.
+  pub fn view<'a, T>(&self, target: &'a mut T) -> View<'a>
+  where T: DrawTarget + ?Sized {
+    target.view(self.viewport).scrolled(self.position.0, self.position.1)
+  }
+
+  pub fn center_on(&mut self, (x, y): (i32, i32)) {
+    self.position = (x - self.viewport.width as i32 / 2, y - self.viewport.height as i32 / 2);
+    self.clamp();
+  }
+
+  /// Scrolls just enough to keep the target inside the deadzone. A zero deadzone keeps it centered.
+  pub fn follow(&mut self, (x, y): (i32, i32)) {
+    let axis = |position: i32, target: i32, view_size: usize, deadzone: usize| {
+      // centered on the same cell as 'center_on' centers on
+      let deadzone = deadzone.clamp(1, view_size.max(1)) as i32;
+      let low = view_size as i32 / 2 - deadzone / 2;
+      let high = low + deadzone - 1;
+      let relative = target - position;
+
+      if relative < low {
+        position - (low - relative)
+      } else if relative > high {
+        position + (relative - high)
+      } else {
+        position
+      }
+    };
+
+    self.position = (
+      axis(self.position.0, x, self.viewport.width, self.deadzone.0),
+      axis(self.position.1, y, self.viewport.height, self.deadzone.1),
+    );
+    self.clamp();
+  }
+
+  /// Keeps the viewport inside the world bounds. Worlds smaller than the viewport are centered in it.
+  pub fn clamp(&mut self) {
+    let Some(world) = self.bounds else { return; };
+
+    let axis = |position: i32, view_size: usize, start: i32, size: usize| {
+      let (view_size, size) = (view_size as i32, size as i32);
+
+      if size <= view_size {
+        start - (view_size - size) / 2
+      } else {
+        position.clamp(start, start + size - view_size)
+      }
+    };
+
+    self.position = (
+      axis(self.position.0, self.viewport.width, world.x, world.width),
+      axis(self.position.1, self.viewport.height, world.y, world.height),
+    );
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // a 20x10 viewport with a 6x4 deadzone, which spans columns 7 to 12 and rows 3 to 6
+  fn camera() -> Camera {
+    Camera::new(Rect::new(0, 0, 20, 10)).deadzone(6, 4)
+  }
+
+  #[test]
+  fn following_inside_the_deadzone_doesnt_scroll() {
+    let mut camera = camera();
+
+    for target in [(7, 3), (12, 6), (10, 5)] {
+      camera.follow(target);
+      assert_eq!(camera.position, (0, 0), "following {:?}", target);
+    }
+  }
+
+  #[test]
+  fn following_out_of_the_deadzone_scrolls_just_enough() {
+    let mut camera = camera();
+
+    camera.follow((15, 5));
+    assert_eq!(camera.position, (3, 0));
+    assert_eq!(camera.world_to_screen((15, 5)), (12, 5), "on the deadzone's edge");
+
+    camera.follow((2, 1));
+    assert_eq!(camera.position, (-5, -2));
+    assert_eq!(camera.world_to_screen((2, 1)), (7, 3));
+
+    let mut centered = camera.deadzone(0, 0);
+    centered.follow((30, 30));
+    assert_eq!(centered.world_to_screen((30, 30)), (10, 5));
+    centered.center_on((31, 31));
+    centered.follow((31, 31));
+    assert_eq!(centered.world_to_screen((31, 31)), (10, 5), "the same cell 'center_on' centers on");
+  }
+
+  #[test]
+  fn the_camera_stays_inside_the_world() {
+    let mut camera = camera().bounds(Rect::new(0, 0, 50, 30));
+
+    camera.center_on((1, 1));
+    assert_eq!(camera.position, (0, 0));
+
+    camera.center_on((49, 29));
+    assert_eq!(camera.position, (30, 20));
+
+    camera.follow((49, 35));
+    assert_eq!(camera.position, (30, 20), "targets outside of the world don't pull it out");
+  }
+
+  #[test]
+  fn small_worlds_are_centered() {
+    let mut camera = camera().bounds(Rect::new(5, 5, 10, 6));
+
+    for target in [(5, 5), (14, 10), (100, -100)] {
+      camera.follow(target);
+      assert_eq!(camera.position, (0, 3), "following {:?}", target);
+    }
+    assert_eq!(camera.world_to_screen((5, 5)), (5, 2));
+  }
+
+  #[test]
+  fn screen_and_world_cells_convert_both_ways() {
+    let mut camera = Camera::new(Rect::new(3, 2, 20, 10));
+    camera.position = (17, -4);
+
+    for y in -4..6 {
+      for x in 17..37 {
+        assert!(camera.is_visible((x, y)));
+        assert_eq!(camera.screen_to_world(camera.world_to_screen((x, y))), Some((x, y)));
+      }
+    }
+    for row in 2..12 {
+      for col in 3..23 {
+        assert_eq!(camera.screen_to_world((col, row)).map(|cell| camera.world_to_screen(cell)), Some((col, row)));
+      }
+    }
+
+    assert_eq!(camera.screen_to_world((3, 2)), Some((17, -4)));
+    assert_eq!(camera.screen_to_world((2, 2)), None, "left of the viewport");
+    assert_eq!(camera.screen_to_world((23, 5)), None, "right of the viewport");
+    assert!(!camera.is_visible((37, 0)));
+  }
+}
@@ -1,4 +1,4 @@
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub struct Color {
   pub r: u8,
   pub g: u8,
@@ -12,9 +12,9 @@ impl Color {
   
   fn sub_safe(self, value: u8) -> Self {
     Color {
-      r: if value >= self.r { 0 } else { self.r - value },
-      g: if value >= self.g { 0 } else { self.g - value },
-      b: if value >= self.b { 0 } else { self.b - value },
+      r: self.r.saturating_sub(value),
+      g: self.g.saturating_sub(value),
+      b: self.b.saturating_sub(value),
     }
   }
 
@@ -51,15 +51,41 @@ impl std::ops::Sub<u8> for Color {
   }
 }
 
+/// Which parts of a Character let the layers below it show through.
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub enum Transparency {
+  #[default]
+  Opaque,
+  Symbol,     // the symbol and its color come from below, only the background is drawn
+  Background, // the background comes from below, only the symbol is drawn
+  Full,       // nothing is drawn
+}
+
 /// Colored 'character' class. Can be seen as a "pixel" to the WindowBuffer.
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub struct Character {
   pub symbol: char,
   pub color: Color,
   pub color_back: Color,
+  pub transparency: Transparency,
 }
 
 impl Character {
+  /// A Character that doesn't hide anything below it.
+  pub fn transparent() -> Self {
+    Character { transparency: Transparency::Full, ..Default::default() }
+  }
+  
+  /// Places this Character over 'below', keeping whatever its transparency lets through.
+  pub fn over(&self, below: &Character) -> Character {
+    match self.transparency {
+      Transparency::Opaque => *self,
+      Transparency::Symbol => Character { color_back: self.color_back, ..*below },
+      Transparency::Background => Character { color_back: below.color_back, transparency: below.transparency, ..*self },
+      Transparency::Full => *below,
+    }
+  }
+  
   pub fn dim_background(&mut self, value: u8) -> Option<Self> {
     if value > self.color_back.min() { return None; }
     
@@ -96,6 +122,6 @@ impl Character {
 
 impl Default for Character {
   fn default() -> Self {
-    Character { symbol: ' ', color: Color{ r: 255, g: 255, b: 255 }, color_back: Color{ r: 0, g: 0, b: 0 } } // custom default value
+    Character { symbol: ' ', color: Color{ r: 255, g: 255, b: 255 }, color_back: Color{ r: 0, g: 0, b: 0 }, transparency: Transparency::Opaque } // custom default value
   }
 }
@@ -1,82 +1,269 @@
 use crate::{Character};
 
+use std::collections::HashMap;
 use anyhow::Result;
 
+/// One layer of the DrawBuffer. Layers with a higher 'z' are drawn over the lower ones.
+struct Layer {
+  z: i32,
+  characters: Vec<Character>,
+}
+
 pub struct DrawBuffer {
   pub width: usize,
   pub height: usize,
-  pub characters: Vec<Character>,
-  
+  pub characters: Vec<Character>, // every layer composited together, this is what gets presented
+
+  layers: Vec<Layer>, // sorted by 'z'
+  layer_names: HashMap<String, i32>,
+  active_layer: usize,
+
   pub text_changed: bool,
 }
 
 impl std::ops::Index<(usize, usize)> for DrawBuffer {
   type Output = Character;
   fn index(&self, i: (usize, usize)) -> &Character {
-    &self.characters[i.1 * self.width as usize + i.0]
+    &self.characters[i.1 * self.width + i.0]
   }
 }
 
 impl DrawBuffer {
+  /// The layer everything is drawn to unless another one is picked. It starts out opaque, the other layers start out transparent.
+  pub const BASE_LAYER: i32 = 0;
+
   pub fn new(p_width: usize, p_height: usize) -> Self {
     DrawBuffer {
       width: p_width,
       height: p_height,
       characters: vec![Character::default(); p_width * p_height], // reserving the used screen space
-      
+
+      layers: vec![Layer { z: Self::BASE_LAYER, characters: vec![Character::default(); p_width * p_height] }],
+      layer_names: HashMap::new(),
+      active_layer: 0,
+
       text_changed: true,
     }
   }
-  
+
   pub fn get_size_usize(&mut self) -> (usize, usize) {
     (self.width, self.height)
   }
-  
+
   pub fn resize(&mut self, p_width: usize, p_height: usize) -> &mut Self {
     self.characters.resize(p_width * p_height, Default::default());
-    
+
+    for layer in &mut self.layers {
+      let empty = Self::empty_char(layer.z);
+      layer.characters.resize(p_width * p_height, empty);
+    }
+
     self.width = p_width;
     self.height = p_height;
-    
+    self.text_changed = true;
+
+    self
+  }
+
+  fn empty_char(z: i32) -> Character {
+    if z == Self::BASE_LAYER { Character::default() } else { Character::transparent() }
+  }
+
+  /// Makes every following draw call go to the layer 'z', creating it if needed.
+  pub fn set_layer(&mut self, z: i32) -> &mut Self {
+    self.active_layer = match self.layers.binary_search_by_key(&z, |layer| layer.z) {
+      Ok(index) => index,
+      Err(index) => {
+        self.layers.insert(index, Layer { z, characters: vec![Character::transparent(); self.width * self.height] });
+        index
+      },
+    };
+
+    self
+  }
+
+  /// Gives the layer 'z' a name that 'set_layer_named' can refer to.
+  pub fn name_layer(&mut self, name: &str, z: i32) -> &mut Self {
+    self.layer_names.insert(name.to_string(), z);
+
     self
   }
-  
+
+  /// Same as 'set_layer', using a name given by 'name_layer'. Panics on unknown names.
+  pub fn set_layer_named(&mut self, name: &str) -> &mut Self {
+    let z = *self.layer_names.get(name).unwrap_or_else(|| panic!("unknown layer '{}'", name));
+
+    self.set_layer(z)
+  }
+
+  /// The 'z' of the layer currently drawn to.
+  pub fn layer(&self) -> i32 {
+    self.layers[self.active_layer].z
+  }
+
+  /// Reads from the active layer. None outside of the buffer.
+  pub fn get_char(&self, col: usize, row: usize) -> Option<Character> {
+    if col >= self.width || row >= self.height { return None; }
+
+    Some(self.layers[self.active_layer].characters[row * self.width + col])
+  }
+
+  /// Writes to the active layer. Writes outside of the buffer are dropped.
   pub fn set_char(&mut self, col: usize, row: usize, character: Character) -> &mut Self {
-    let char_ref = &mut self.characters[row * self.width + col];
+    if col >= self.width || row >= self.height { return self; }
+
+    let char_ref = &mut self.layers[self.active_layer].characters[row * self.width + col];
     if *char_ref != character {
       *char_ref = character;
       self.text_changed = true;
     }
-    
+
     self
   }
-  
+
+  /// Empties the active layer. The base layer goes back to blank cells, the others become transparent.
   pub fn clear(&mut self) -> &mut Self {
-    for i in 0..(self.height) {
-      for j in 0..(self.width) {
-        let char_ref = &mut self.characters[i * self.width + j];
-        if *char_ref != Default::default() {
-          *char_ref = Default::default();
-          self.text_changed = true;        
-        }
-      }
-    }
-    
-    self
+    let empty = Self::empty_char(self.layer());
+
+    self.fill_char(empty)
   }
-  
+
   pub fn fill_char(&mut self, character: Character) -> &mut Self {
     for i in 0..(self.height) {
       for j in 0..(self.width) {
-        let char_ref = &mut self.characters[i * self.width + j];
+        let char_ref = &mut self.layers[self.active_layer].characters[i * self.width + j];
         if *char_ref != character {
           *char_ref = character;
-          self.text_changed = true;        
+          self.text_changed = true;
         }
       }
     }
-    
+
     self
   }
-  
+
+  /// Composites every layer, from the lowest 'z' up, into 'characters'. Called by the Engine before presenting.
+  pub fn compose(&mut self) -> &mut Self {
+    if !self.text_changed { return self; }
+
+    for (index, out) in self.characters.iter_mut().enumerate() {
+      let mut composed = Character::default();
+
+      for layer in &self.layers {
+        composed = layer.characters[index].over(&composed);
+      }
+
+      *out = composed;
+    }
+
+    self
+  }
+
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{Color, Transparency};
+
+  const RED: Color = Color { r: 255, g: 0, b: 0 };
+  const BLUE: Color = Color { r: 0, g: 0, b: 255 };
+  const GREEN: Color = Color { r: 0, g: 255, b: 0 };
+
+  fn character(symbol: char, color: Color, color_back: Color, transparency: Transparency) -> Character {
+    Character { symbol, color, color_back, transparency }
+  }
+
+  // a red 'a' on blue in the base layer, with 'top' drawn over it on layer 1
+  fn composed(top: Character) -> Character {
+    let mut db = DrawBuffer::new(1, 1);
+    db.set_char(0, 0, character('a', RED, BLUE, Transparency::Opaque));
+    db.set_layer(1).set_char(0, 0, top);
+    db.compose();
+
+    db[(0, 0)]
+  }
+
+  #[test]
+  fn higher_layers_are_drawn_over_lower_ones() {
+    let mut db = DrawBuffer::new(3, 1);
+
+    // created out of order, the 'z' decides
+    db.set_layer(5).set_char(0, 0, Character { symbol: 'c', ..Default::default() });
+    db.set_layer(-1).set_char(0, 0, Character { symbol: 'z', ..Default::default() });
+    db.set_char(1, 0, Character { symbol: 'z', ..Default::default() });
+    db.set_layer(DrawBuffer::BASE_LAYER).set_char(0, 0, Character { symbol: 'b', ..Default::default() });
+    db.set_char(1, 0, Character { symbol: 'b', ..Default::default() });
+    db.compose();
+
+    assert_eq!(db[(0, 0)].symbol, 'c');
+    assert_eq!(db[(1, 0)].symbol, 'b', "the base layer hides the ones under it");
+    assert_eq!(db[(2, 0)].symbol, ' ');
+  }
+
+  #[test]
+  fn new_layers_start_transparent() {
+    let mut db = DrawBuffer::new(2, 2);
+    db.set_char(1, 1, Character { symbol: 'a', ..Default::default() });
+    db.set_layer(3);
+    db.compose();
+
+    assert_eq!(db.get_char(1, 1).unwrap().transparency, Transparency::Full);
+    assert_eq!(db[(1, 1)].symbol, 'a');
+
+    db.set_char(1, 1, Character { symbol: 'b', ..Default::default() }).clear().compose();
+    assert_eq!(db[(1, 1)].symbol, 'a', "clearing a layer makes it transparent again");
+  }
+
+  #[test]
+  fn opaque_characters_hide_everything_below() {
+    let top = character('b', GREEN, GREEN, Transparency::Opaque);
+
+    assert_eq!(composed(top), top);
+  }
+
+  #[test]
+  fn symbol_transparency_only_draws_the_background() {
+    let top = character('b', GREEN, GREEN, Transparency::Symbol);
+
+    assert_eq!(composed(top), character('a', RED, GREEN, Transparency::Opaque));
+  }
+
+  #[test]
+  fn background_transparency_only_draws_the_symbol() {
+    let top = character('b', GREEN, GREEN, Transparency::Background);
+
+    assert_eq!(composed(top), character('b', GREEN, BLUE, Transparency::Opaque));
+  }
+
+  #[test]
+  fn full_transparency_draws_nothing() {
+    let top = character('b', GREEN, GREEN, Transparency::Full);
+
+    assert_eq!(composed(top), character('a', RED, BLUE, Transparency::Opaque));
+  }
+
+  #[test]
+  fn transparency_stacks_across_layers() {
+    let mut db = DrawBuffer::new(1, 1);
+    db.set_char(0, 0, character('a', RED, BLUE, Transparency::Opaque));
+    db.set_layer(1).set_char(0, 0, character('b', GREEN, RED, Transparency::Background));
+    db.set_layer(2).set_char(0, 0, character('c', BLUE, GREEN, Transparency::Symbol));
+    db.compose();
+
+    assert_eq!(db[(0, 0)], character('b', GREEN, GREEN, Transparency::Opaque));
+  }
+
+  #[test]
+  fn writes_outside_the_buffer_are_dropped() {
+    let mut db = DrawBuffer::new(2, 2);
+    db.compose();
+    db.text_changed = false;
+
+    db.set_char(2, 0, Character { symbol: 'a', ..Default::default() });
+    db.set_char(0, 2, Character { symbol: 'a', ..Default::default() });
+
+    assert!(!db.text_changed);
+    assert!(db.get_char(2, 0).is_none());
+  }
 }
@@ -1,47 +1,485 @@
-use crate::{/*WindowWrapper, AsciiInterface,*/ DrawBuffer, InputManager, GameState, InputDispatcher, KeyState};
+use crate::{/*WindowWrapper, AsciiInterface,*/ DrawBuffer, Camera, Rect, InputManager, GameState, InputDispatcher, InputContext, KeyState, KeyChord, KeyRepeat, BindingId, Propagation, Backend, Scene, SceneStack, SceneCommand, Recording, Recorder, Player};
 
 use crossterm::{terminal, execute, cursor, event::KeyCode};
 use std::time::{Duration, Instant};
 use std::io::{stdout};
+use std::path::PathBuf;
 use anyhow::Result;
+use rand::{SeedableRng, rngs::StdRng};
+
+/// Options the Engine is created with.
+#[derive(Clone, Default)]
+pub struct EngineConfig {
+  /// Seed for the Engine's RNG. A random seed is picked when it's None.
+  pub seed: Option<u64>,
+  /// Writes every frame's input to this file.
+  pub record: Option<PathBuf>,
+  /// Plays the input back from this file instead of the player's. Its seed overrides 'seed'.
+  pub replay: Option<PathBuf>,
+  /// The player's keymap. It's loaded over the game's default actions, see 'Engine::load_keymap'.
+  pub keymap: Option<PathBuf>,
+  /// How long a key may go without a press or repeat before it counts as released, on backends that don't report
+  /// releases (see 'KeyReleaseMode::Synthesized'). The InputManager's default when None.
+  pub release_timeout: Option<Duration>,
+}
+
+/// What happens when the screen and the DrawBuffer don't have the same size.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum ResizePolicy {
+  /// The DrawBuffer is centered on the screen, anything that doesn't fit is cut off evenly on both sides.
+  #[default]
+  Letterbox,
+  /// Like Letterbox, but a DrawBuffer bigger than the screen is cut around the Engine's focus point instead.
+  Crop,
+  /// The DrawBuffer is resized to the screen whenever the screen changes size.
+  Resize,
+}
+
+impl<GS> InputContext for Engine<GS> {
+  fn input(&self) -> &InputManager {
+    &self.inp_man
+  }
+}
 
 /// Base engige struct. Controls input redirection and stores the ASCII screen.
 pub struct Engine<GS> { // <GameState, Wrapper>
   pub framerate: u64,
   pub fixed_time_step: Duration,
   pub frame_counter: u64,
-  
+  pub start_of_frame: Instant,
+
+  screen_size: (usize, usize),
+  pub resize_policy: ResizePolicy,
+  pub focus: Option<(usize, usize)>, // DrawBuffer cell kept on screen by ResizePolicy::Crop
+  pub camera: Camera, // shows the whole DrawBuffer until the game sets it up
+
+  seed: u64,
+  rng: StdRng,
+  recorder: Option<Recorder>,
+  player: Option<Player>,
+  keymap: Option<PathBuf>,
+
   pub db: DrawBuffer, // DrawBuffer<std::io::Stdout>,
   pub inp_man: InputManager,
-  pub inp_dis: InputDispatcher<GS>,
+  pub inp_dis: InputDispatcher<GS, Engine<GS>>,
+
+  scenes: SceneStack<GS>,
+  pub(crate) scene_commands: Vec<SceneCommand<GS>>,
 }
 
 impl<GS> Engine<GS>
 where GS: GameState {
   pub fn new(screen_size: (usize, usize)) -> Self {
-    // use std::io::stdout;
-
     let (term_w, term_h) = screen_size;
-  
+    let seed = rand::random();
+
+
     Engine::<GS> {
       framerate: 10,
       fixed_time_step: Duration::from_secs_f32(1.0 / 10.0),
       frame_counter: 0,
+      start_of_frame: Instant::now(),
+
+      screen_size,
+      resize_policy: ResizePolicy::default(),
+      focus: None,
+      camera: Camera::new(Rect::new(0, 0, term_w, term_h)),
+
+      seed,
+      rng: StdRng::seed_from_u64(seed),
+      recorder: None,
+      player: None,
+      keymap: None,
 
       db: DrawBuffer::new(term_w, term_h),
       inp_man: InputManager::new(),
-      inp_dis: InputDispatcher::<GS>::new(),
+      inp_dis: InputDispatcher::new(),
+
+      scenes: SceneStack::default(),
+      scene_commands: Vec::new(),
     }
   }
-  
+
+  pub fn with_config(screen_size: (usize, usize), config: EngineConfig) -> Result<Self> {
+    let mut engine = Self::new(screen_size);
+
+    let mut seed = config.seed;
+
+    if let Some(path) = &config.replay {
+      let recording = Recording::load(path)?;
+      seed = Some(recording.seed);
+      engine.player = Some(Player::new(recording));
+    }
+
+    if let Some(seed) = seed {
+      engine.set_seed(seed);
+    }
+
+    if let Some(path) = &config.record {
+      engine.recorder = Some(Recorder::create(path, engine.seed)?);
+    }
+
+    engine.keymap = config.keymap;
+
+    if let Some(timeout) = config.release_timeout {
+      engine.inp_man.release_timeout = timeout;
+    }
+
+    Ok(engine)
+  }
+
+  pub fn is_replaying(&self) -> bool {
+    self.player.is_some()
+  }
+
+  /// Size of the backend's screen in cells. It doesn't have to match the DrawBuffer's size.
+  pub fn screen_size(&self) -> (usize, usize) {
+    self.screen_size
+  }
+
+  /// The world cell under the mouse, through the camera. None when the mouse is outside of the camera's viewport.
+  pub fn mouse_world(&self) -> Option<(i32, i32)> {
+    self.camera.screen_to_world(self.inp_man.mouse_position?)
+  }
+
+  /// Where the DrawBuffer's top-left cell lands on the screen, according to the resize policy.
+  pub fn screen_offset(&self) -> (i32, i32) {
+    let focus = match self.resize_policy {
+      ResizePolicy::Crop => self.focus,
+      _ => None,
+    };
+
+    let axis_offset = |screen: usize, buffer: usize, focus: Option<usize>| {
+      let (screen, buffer) = (screen as i32, buffer as i32);
+
+      match focus {
+        Some(focus) if buffer > screen => (screen / 2 - focus as i32).clamp(screen - buffer, 0),
+        _ => (screen - buffer) / 2,
+      }
+    };
+
+    (
+      axis_offset(self.screen_size.0, self.db.width, focus.map(|f| f.0)),
+      axis_offset(self.screen_size.1, self.db.height, focus.map(|f| f.1)),
+    )
+  }
+
   pub fn set_framerate(&mut self, new_fps: u64) {
     self.framerate = new_fps;
     self.fixed_time_step = Duration::from_secs_f32(1.0 / new_fps as f32);
   }
-  
-  pub fn bind<F>(&mut self, key: KeyCode, key_state: KeyState, callback: F)
-  where F: FnMut(&mut GS) + 'static {
-    self.inp_dis.bind(key, key_state, callback);
+
+  /// The Engine's RNG. All of the game's randomness should come from here, so a seed reproduces a whole session.
+  pub fn rng(&mut self) -> &mut StdRng {
+    &mut self.rng
+  }
+
+  pub fn seed(&self) -> u64 {
+    self.seed
+  }
+
+  /// Restarts the RNG from a new seed.
+  pub fn set_seed(&mut self, seed: u64) {
+    self.seed = seed;
+    self.rng = StdRng::seed_from_u64(seed);
+  }
+
+  pub fn bind<K, F, R>(&mut self, key: K, key_state: KeyState, callback: F) -> BindingId
+  where K: Into<KeyChord>, F: FnMut(&mut GS, &mut Engine<GS>) -> R + 'static, R: Into<Propagation> {
+    self.inp_dis.bind(key, key_state, callback)
+  }
+
+  /// Subscribe a function to a named action of 'inp_man.actions'.
+  pub fn bind_action<F, R>(&mut self, action: &str, key_state: KeyState, callback: F) -> BindingId
+  where F: FnMut(&mut GS, &mut Engine<GS>) -> R + 'static, R: Into<Propagation> {
+    self.inp_dis.bind_action(action, key_state, callback)
+  }
+
+  /// Binds a function that fires once when the key goes down, then again at the KeyRepeat's pace while it's held.
+  pub fn bind_repeating<K, F, R>(&mut self, key: K, repeat: KeyRepeat, callback: F) -> BindingId
+  where K: Into<KeyChord>, F: FnMut(&mut GS, &mut Engine<GS>) -> R + 'static, R: Into<Propagation> {
+    self.inp_dis.bind_repeating(key, repeat, callback)
+  }
+
+  pub fn bind_action_repeating<F, R>(&mut self, action: &str, repeat: KeyRepeat, callback: F) -> BindingId
+  where F: FnMut(&mut GS, &mut Engine<GS>) -> R + 'static, R: Into<Propagation> {
+    self.inp_dis.bind_action_repeating(action, repeat, callback)
+  }
+
+  pub fn unbind(&mut self, id: BindingId) -> bool {
+    self.inp_dis.unbind(id)
+  }
+
+  pub fn action_pressed(&self, action: &str) -> bool {
+    self.inp_man.action_pressed(action)
+  }
+
+  pub fn action_down(&self, action: &str) -> bool {
+    self.inp_man.action_down(action)
+  }
+
+  pub fn action_released(&self, action: &str) -> bool {
+    self.inp_man.action_released(action)
+  }
+
+  /// Loads the configured keymap over the actions bound so far.
+  /// If there's no keymap file yet, the current actions are written to it, so the player has one to edit.
+  pub fn load_keymap(&mut self) -> Result<()> {
+    match &self.keymap {
+      Some(path) if path.exists() => self.inp_man.actions.load(path),
+      Some(path) => self.inp_man.actions.save(path),
+      None => Ok(()),
+    }
+  }
+
+  /// Writes the current actions to the configured keymap file, e.g. after the player rebound a key.
+  pub fn save_keymap(&self) -> Result<()> {
+    match &self.keymap {
+      Some(path) => self.inp_man.actions.save(path),
+      None => Ok(()),
+    }
+  }
+
+  /// Queues a scene to be pushed on top of the scene stack. Takes effect before the next scene update.
+  pub fn push_scene<S>(&mut self, scene: S)
+  where S: Scene<GS> {
+    self.scene_commands.push(SceneCommand::push(scene));
+  }
+
+  /// Queues the removal of the top scene.
+  pub fn pop_scene(&mut self) {
+    self.scene_commands.push(SceneCommand::Pop);
+  }
+
+  /// Queues the replacement of the top scene.
+  pub fn replace_scene<S>(&mut self, scene: S)
+  where S: Scene<GS> {
+    self.scene_commands.push(SceneCommand::replace(scene));
+  }
+
+  /// Runs a single frame: input, dispatch, update, draw, present. Returns false once the game should stop.
+  pub fn frame<B>(&mut self, game_state: &mut GS, backend: &mut B) -> Result<bool>
+  where B: Backend {
+    if backend.should_quit() || !game_state.should_run() {
+      return Ok(false);
+    }
+
+    backend.pump_input(&mut self.inp_man)?;
+    self.inp_man.synthesize_releases();
+
+    let screen_size = backend.size();
+    if screen_size != self.screen_size {
+      self.screen_size = screen_size;
+
+      if self.resize_policy == ResizePolicy::Resize {
+        // a camera showing the whole DrawBuffer keeps doing so
+        let camera_fills_db = self.camera.viewport == Rect::new(0, 0, self.db.width, self.db.height);
+
+        self.db.resize(screen_size.0, screen_size.1);
+
+        if camera_fills_db {
+          self.camera.viewport = Rect::new(0, 0, self.db.width, self.db.height);
+          self.camera.clamp();
+        }
+      }
+
+      game_state.on_resize(self, screen_size);
+    }
+
+    if let Some(player) = &mut self.player && !player.play_frame(&mut self.inp_man) {
+      // the recording ran out, the player takes over from here
+      self.player = None;
+      self.inp_man.key_events.clear();
+      self.inp_man.keypad_keys.clear();
+      self.inp_man.mouse_buttons.clear();
+      self.inp_man.modifiers = crossterm::event::KeyModifiers::empty();
+    }
+
+    if let Some(recorder) = &mut self.recorder {
+      recorder.record_frame(&self.inp_man)?;
+    }
+
+    // the callbacks get the Engine, so the bindings are moved out of it while they run
+    let mut inp_dis = self.inp_dis.take();
+    inp_dis.dispatch(game_state, self);
+    self.inp_dis.restore(inp_dis);
+
+    // the stack is moved out while the scenes run, so they can freely borrow the Engine
+    let mut scenes = std::mem::take(&mut self.scenes);
+    scenes.apply_queued(game_state, self);
+    scenes.dispatch(self);
+
+    game_state.update(self);
+    scenes.update(game_state, self);
+
+    game_state.draw(self);
+    scenes.draw(game_state, self);
+    self.scenes = scenes;
+
+    self.db.compose();
+    let offset = self.screen_offset();
+    backend.present(&mut self.db, offset)?;
+
+    self.inp_man.cycle_events();
+    self.inp_man.time += self.fixed_time_step;
+    self.frame_counter += 1;
+
+    Ok(true)
+  }
+
+  fn sync_frame(&mut self) {
+    use std::thread;
+
+    let end_of_frame = Instant::now();
+    let passed_duration = end_of_frame.duration_since(self.start_of_frame);
+
+    let remaining_duration = self.fixed_time_step.saturating_sub(passed_duration);
+
+    thread::sleep(remaining_duration);
+
+    self.start_of_frame += self.fixed_time_step;
+  }
+
+  fn game_loop<B>(&mut self, game_state: &mut GS, backend: &mut B) -> Result<()>
+  where B: Backend {
+    self.start_of_frame = Instant::now();
+
+    while self.frame(game_state, backend)? {
+      if backend.syncs_frames() {
+        self.sync_frame();
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Sets the backend up, runs frames until the game stops and tears the backend down again.
+  pub fn run<B>(&mut self, game_state: &mut GS, backend: &mut B) -> Result<()>
+  where B: Backend {
+    backend.init()?;
+    self.inp_man.set_key_release_mode(backend.key_release_mode());
+
+    let loop_result = self.game_loop(game_state, backend);
+
+    let shutdown_result = backend.shutdown();
+
+    loop_result.and(shutdown_result)
+  }
+
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::HeadlessGame;
+  use crossterm::event::{KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+
+  struct Resized {
+    sizes: Vec<(usize, usize)>,
+  }
+
+  impl GameState for Resized {
+    fn new(_ctx: &mut Engine<Self>) -> Self {
+      Self { sizes: vec![] }
+    }
+
+    fn update(&mut self, _ctx: &mut Engine<Self>) {}
+
+    fn draw(&mut self, _ctx: &mut Engine<Self>) {}
+
+    fn should_run(&mut self) -> bool {
+      true
+    }
+
+    fn on_resize(&mut self, _ctx: &mut Engine<Self>, screen_size: (usize, usize)) {
+      self.sizes.push(screen_size);
+    }
+  }
+
+  // a 20x10 game whose screen is then resized to 'screen_size'
+  fn resized(policy: ResizePolicy, screen_size: (usize, usize)) -> HeadlessGame<Resized> {
+    let mut game = HeadlessGame::<Resized>::new((20, 10)).unwrap();
+    game.engine().resize_policy = policy;
+
+    game.backend().set_size(screen_size);
+    game.step().unwrap();
+
+    game
+  }
+
+  fn mouse(kind: MouseEventKind, column: u16, row: u16) -> MouseEvent {
+    MouseEvent { kind, column, row, modifiers: KeyModifiers::empty() }
+  }
+
+  #[test]
+  fn letterbox_centers_the_buffer() {
+    let mut game = resized(ResizePolicy::Letterbox, (30, 14));
+
+    assert_eq!(game.engine().screen_size(), (30, 14));
+    assert_eq!((game.db().width, game.db().height), (20, 10));
+    assert_eq!(game.engine().screen_offset(), (5, 2));
+    assert_eq!(game.game_state().sizes, [(30, 14)]);
+
+    // a screen smaller than the buffer cuts it off evenly on both sides
+    game.backend().set_size((16, 7));
+    game.step().unwrap();
+    assert_eq!((game.db().width, game.db().height), (20, 10));
+    assert_eq!(game.engine().screen_offset(), (-2, -1));
+  }
+
+  #[test]
+  fn crop_keeps_the_focus_on_screen() {
+    let mut game = resized(ResizePolicy::Crop, (10, 6));
+    assert_eq!((game.db().width, game.db().height), (20, 10));
+
+    // without a focus it's the same as Letterbox
+    assert_eq!(game.engine().screen_offset(), (-5, -2));
+
+    game.engine().focus = Some((12, 4));
+    assert_eq!(game.engine().screen_offset(), (-7, -1), "the focus is centered");
+
+    game.engine().focus = Some((18, 9));
+    assert_eq!(game.engine().screen_offset(), (-10, -4), "the buffer's edge stays on the screen's edge");
+
+    game.engine().focus = Some((1, 1));
+    assert_eq!(game.engine().screen_offset(), (0, 0));
+
+    // a buffer smaller than the screen is centered whatever the focus
+    game.backend().set_size((30, 14));
+    game.step().unwrap();
+    assert_eq!(game.engine().screen_offset(), (5, 2));
+  }
+
+  #[test]
+  fn resize_follows_the_screen() {
+    let mut game = resized(ResizePolicy::Resize, (30, 14));
+
+    assert_eq!((game.db().width, game.db().height), (30, 14));
+    assert_eq!(game.engine().screen_offset(), (0, 0));
+    assert_eq!(game.engine().camera.viewport, Rect::new(0, 0, 30, 14), "a camera over the whole buffer keeps covering it");
+
+    game.backend().set_size((12, 5));
+    game.step().unwrap();
+    assert_eq!((game.db().width, game.db().height), (12, 5));
+    assert_eq!(game.engine().screen_offset(), (0, 0));
+    assert_eq!(game.game_state().sizes, [(30, 14), (12, 5)]);
+  }
+
+  #[test]
+  fn letterboxed_mouse_positions_are_in_buffer_cells() {
+    let mut game = resized(ResizePolicy::Letterbox, (30, 14));
+
+    game.inject_mouse(mouse(MouseEventKind::Moved, 5, 2)).step().unwrap();
+    assert_eq!(game.engine().inp_man.mouse_position, Some((0, 0)));
+
+    game.inject_mouse(mouse(MouseEventKind::Down(MouseButton::Left), 24, 11)).step().unwrap();
+    assert_eq!(game.engine().inp_man.mouse_position, Some((19, 9)));
+    assert_ne!(game.engine().inp_man.get_mouse_button(MouseButton::Left), KeyState::Unactive);
+
+    // the borders around the buffer aren't part of it
+    for (column, row) in [(4, 2), (5, 1), (25, 11), (24, 12)] {
+      game.inject_mouse(mouse(MouseEventKind::Moved, column, row)).step().unwrap();
+      assert_eq!(game.engine().inp_man.mouse_position, None, "at ({}, {})", column, row);
+    }
   }
-  
 }
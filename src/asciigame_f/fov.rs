@@ -0,0 +1,214 @@
+use std::collections::HashSet;
+
+// Symmetric shadowcasting, after Albert Ford's write-up. Each quadrant is scanned row by row away from the origin,
+// and every run of opaque cells narrows the slopes the next rows are scanned between.
+// Slopes are kept as fractions, so nothing is lost to float rounding on big maps.
+
+/// Every cell visible from 'origin' within 'radius' (euclidean), including the origin and the opaque cells that bound the view.
+/// Visibility is symmetric: if A sees B, B sees A. 'is_opaque' is asked about cells outside of the map too, it should say true.
+pub fn field_of_view<F>(origin: (i32, i32), radius: usize, is_opaque: F) -> HashSet<(i32, i32)>
+where F: Fn(i32, i32) -> bool {
+  let mut scan = Scan {
+    origin,
+    radius: radius as i32,
+    quadrant: Quadrant::North,
+    is_opaque,
+    visible: HashSet::new(),
+  };
+
+  scan.visible.insert(origin);
+
+  for quadrant in [Quadrant::North, Quadrant::East, Quadrant::South, Quadrant::West] {
+    scan.quadrant = quadrant;
+    scan.row(1, Slope::new(-1, 1), Slope::new(1, 1));
+  }
+
+  scan.visible
+}
+
+#[derive(Clone, Copy)]
+enum Quadrant {
+  North,
+  East,
+  South,
+  West,
+}
+
+/// A fraction with a positive denominator.
+#[derive(Clone, Copy)]
+struct Slope {
+  num: i32,
+  den: i32,
+}
+
+impl Slope {
+  fn new(num: i32, den: i32) -> Self {
+    Self { num, den }
+  }
+
+  /// The slope through the near corner of a cell's left edge.
+  fn of_cell(depth: i32, col: i32) -> Self {
+    Self::new(2 * col - 1, 2 * depth)
+  }
+
+  /// depth * slope, with halves rounded up.
+  fn col_rounded_up(&self, depth: i32) -> i32 {
+    (2 * depth * self.num + self.den).div_euclid(2 * self.den)
+  }
+
+  /// depth * slope, with halves rounded down.
+  fn col_rounded_down(&self, depth: i32) -> i32 {
+    -(self.den - 2 * depth * self.num).div_euclid(2 * self.den)
+  }
+}
+
+struct Scan<F> {
+  origin: (i32, i32),
+  radius: i32,
+  quadrant: Quadrant,
+  is_opaque: F,
+  visible: HashSet<(i32, i32)>,
+}
+
+impl<F> Scan<F>
+where F: Fn(i32, i32) -> bool {
+  /// Map cell of a (depth, col) position in the current quadrant.
+  fn cell(&self, depth: i32, col: i32) -> (i32, i32) {
+    let (x, y) = self.origin;
+
+    match self.quadrant {
+      Quadrant::North => (x + col, y - depth),
+      Quadrant::South => (x + col, y + depth),
+      Quadrant::East => (x + depth, y + col),
+      Quadrant::West => (x - depth, y + col),
+    }
+  }
+
+  fn row(&mut self, depth: i32, mut start: Slope, end: Slope) {
+    if depth > self.radius { return; }
+
+    let mut prev_opaque = None;
+
+    for col in start.col_rounded_up(depth)..=end.col_rounded_down(depth) {
+      let (x, y) = self.cell(depth, col);
+      let opaque = (self.is_opaque)(x, y);
+
+      // floor cells are only seen when their center is inside the scanned slopes, that's what makes it symmetric
+      let symmetric = col * start.den >= depth * start.num && col * end.den <= depth * end.num;
+      let in_radius = depth * depth + col * col <= self.radius * self.radius;
+
+      if in_radius && (opaque || symmetric) {
+        self.visible.insert((x, y));
+      }
+
+      match (prev_opaque, opaque) {
+        (Some(true), false) => start = Slope::of_cell(depth, col),
+        (Some(false), true) => self.row(depth + 1, start, Slope::of_cell(depth, col)),
+        _ => (),
+      }
+
+      prev_opaque = Some(opaque);
+    }
+
+    if prev_opaque == Some(false) {
+      self.row(depth + 1, start, end);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // '#' is opaque, so is everything outside of the grid
+  fn opaque<'a>(grid: &'a [&'a str]) -> impl Fn(i32, i32) -> bool + 'a {
+    move |x, y| {
+      let (Ok(x), Ok(y)) = (usize::try_from(x), usize::try_from(y)) else { return true; };
+
+      grid.get(y).and_then(|row| row.as_bytes().get(x)).is_none_or(|&c| c == b'#')
+    }
+  }
+
+  fn cells(grid: &[&str]) -> Vec<(i32, i32)> {
+    (0..grid.len() as i32).flat_map(|y| (0..grid[0].len() as i32).map(move |x| (x, y))).collect()
+  }
+
+  const OPEN: [&str; 15] = ["..............."; 15];
+
+  #[test]
+  fn visibility_is_symmetric() {
+    let grid = [
+      "##############",
+      "#......#.....#",
+      "#.#..........#",
+      "#....##...#..#",
+      "#..#.....#...#",
+      "#......#.....#",
+      "#.##.....#.#.#",
+      "#............#",
+      "##############",
+    ];
+    let is_opaque = opaque(&grid);
+    let floor: Vec<(i32, i32)> = cells(&grid).into_iter().filter(|&(x, y)| !is_opaque(x, y)).collect();
+    let seen: Vec<HashSet<(i32, i32)>> = floor.iter().map(|&cell| field_of_view(cell, 20, &is_opaque)).collect();
+
+    for (a, seen_from_a) in floor.iter().zip(&seen) {
+      for (b, seen_from_b) in floor.iter().zip(&seen) {
+        assert_eq!(seen_from_a.contains(b), seen_from_b.contains(a), "{:?} and {:?}", a, b);
+      }
+    }
+  }
+
+  #[test]
+  fn a_pillar_only_shadows_the_cells_behind_it() {
+    let mut grid = OPEN;
+    grid[7] = ".........#.....";
+    let visible = field_of_view((7, 7), 20, opaque(&grid));
+
+    let hidden: Vec<(i32, i32)> = cells(&grid).into_iter().filter(|cell| !visible.contains(cell)).collect();
+    assert!(hidden.contains(&(10, 7)) && hidden.contains(&(14, 7)));
+
+    // straight behind the pillar, in a cone that narrows towards it
+    for &(x, y) in &hidden {
+      assert!(x > 9 && (y - 7).abs() * 2 <= x - 9, "{:?} is hidden", (x, y));
+    }
+    assert!(visible.contains(&(9, 7)), "the pillar itself is seen");
+    assert!(visible.contains(&(10, 6)) && visible.contains(&(10, 8)));
+  }
+
+  #[test]
+  fn lit_walls_are_visible() {
+    let grid = [
+      "#########",
+      "#.......#",
+      "#.......#",
+      "#.......#",
+      "#########",
+    ];
+    let visible = field_of_view((3, 2), 20, opaque(&grid));
+
+    assert_eq!(visible.len(), grid.len() * grid[0].len(), "a convex room sees all of its walls");
+  }
+
+  #[test]
+  fn nothing_past_the_radius_is_visible() {
+    let visible = field_of_view((7, 7), 3, opaque(&OPEN));
+
+    for &(x, y) in &visible {
+      assert!((x - 7).pow(2) + (y - 7).pow(2) <= 9, "{:?} is too far", (x, y));
+    }
+    for cell in [(10, 7), (4, 7), (7, 10), (7, 4), (9, 9)] {
+      assert!(visible.contains(&cell), "{:?} is in range", cell);
+    }
+    assert!(!visible.contains(&(10, 8)));
+  }
+
+  #[test]
+  fn the_origin_is_always_visible() {
+    assert_eq!(field_of_view((7, 7), 0, opaque(&OPEN)), HashSet::from([(7, 7)]));
+
+    // even from inside a wall
+    let walled = ["###", "###", "###"];
+    assert!(field_of_view((1, 1), 5, opaque(&walled)).contains(&(1, 1)));
+  }
+}
@@ -0,0 +1,74 @@
+use crate::{GameState, Engine, EngineConfig, Backend, DrawBuffer};
+
+use anyhow::Result;
+
+/// Game. Ties a GameState and its Engine to a Backend. The frame loop itself is in 'Engine::run'.
+pub struct Game<GS, B>
+where GS: GameState, B: Backend {
+  engine: Engine<GS>,
+  game_state: GS,
+  backend: B,
+}
+
+impl<GS, B> Game<GS, B>
+where GS: GameState, B: Backend {
+
+  pub fn with_backend(backend: B) -> Result<Self> {
+    Self::with_config(backend, EngineConfig::default())
+  }
+
+  pub fn with_config(backend: B, config: EngineConfig) -> Result<Self> {
+    Self::with_init(backend, config, GS::new)
+  }
+
+  /// Like 'with_config', but the GameState is made by 'init' instead of 'GameState::new', e.g. to hand it options of its own.
+  pub fn with_init<F>(backend: B, config: EngineConfig, init: F) -> Result<Self>
+  where F: FnOnce(&mut Engine<GS>) -> GS {
+    let mut eng = Engine::<GS>::with_config(backend.size(), config)?;
+    let gs = init(&mut eng);
+    eng.load_keymap()?; // over the defaults the GameState just bound
+
+    Ok(Self {
+      engine: eng,
+      game_state: gs,
+      backend,
+    })
+  }
+
+  pub fn run(&mut self) -> Result<()> {
+    self.engine.run(&mut self.game_state, &mut self.backend)
+  }
+
+  /// Runs a single frame, without any frame syncing. Returns false once the game should stop.
+  pub fn step(&mut self) -> Result<bool> {
+    self.engine.frame(&mut self.game_state, &mut self.backend)
+  }
+
+  /// Runs up to 'frames' frames. Returns the number of frames that actually ran.
+  pub fn step_n(&mut self, frames: u64) -> Result<u64> {
+    let mut ran = 0;
+
+    while ran < frames && self.step()? {
+      ran += 1;
+    }
+
+    Ok(ran)
+  }
+
+  pub fn db(&self) -> &DrawBuffer {
+    &self.engine.db
+  }
+
+  pub fn engine(&mut self) -> &mut Engine<GS> {
+    &mut self.engine
+  }
+
+  pub fn game_state(&mut self) -> &mut GS {
+    &mut self.game_state
+  }
+
+  pub fn backend(&mut self) -> &mut B {
+    &mut self.backend
+  }
+
+}
@@ -2,7 +2,7 @@ use crate::{Engine};
 
 /// A Generic struct that implements the basic 'update' and 'draw' game-logic methods.
 /// It requires the most basic methods so that the Game struct can utilize it. 
-pub trait GameState: Sized {
+pub trait GameState: Sized + 'static {
   fn new(ctx: &mut Engine<Self>) -> Self;
 
   fn update(&mut self, ctx: &mut Engine<Self>) -> ();
@@ -10,4 +10,7 @@ pub trait GameState: Sized {
   fn draw(&mut self, ctx: &mut Engine<Self>) -> ();
   
   fn should_run(&mut self) -> bool;
+  
+  /// Called when the backend's screen changes size, with the new size in cells.
+  fn on_resize(&mut self, _ctx: &mut Engine<Self>, _screen_size: (usize, usize)) {}
 }
@@ -0,0 +1,116 @@
+use crate::{GameState, Game, Backend, DrawBuffer, InputManager};
+
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyEventState, KeyModifiers, MouseEvent};
+use anyhow::Result;
+
+// HeadlessBackend. Has a fixed size, shows nothing and only receives the events injected into it.
+pub struct HeadlessBackend {
+  size: (usize, usize),
+  pending_events: Vec<Event>,
+  offset: (i32, i32), // from the last present, mouse events are translated with it like on a terminal
+  db_size: (usize, usize),
+}
+
+impl HeadlessBackend {
+  pub fn new(screen_size: (usize, usize)) -> Self {
+    Self {
+      size: screen_size,
+      pending_events: Vec::new(),
+      offset: (0, 0),
+      db_size: screen_size,
+    }
+  }
+
+  /// Changes the reported screen size, as if the terminal or window had been resized.
+  pub fn set_size(&mut self, screen_size: (usize, usize)) -> &mut Self {
+    self.size = screen_size;
+
+    self
+  }
+
+  /// Queues a raw key event. It's fed to the InputManager at the start of the next frame.
+  pub fn inject_key(&mut self, key_event: KeyEvent) -> &mut Self {
+    self.pending_events.push(Event::Key(key_event));
+
+    self
+  }
+
+  /// Queues a raw mouse event, in screen cells. It's fed to the InputManager at the start of the next frame.
+  pub fn inject_mouse(&mut self, mouse_event: MouseEvent) -> &mut Self {
+    self.pending_events.push(Event::Mouse(mouse_event));
+
+    self
+  }
+}
+
+impl Backend for HeadlessBackend {
+  fn size(&self) -> (usize, usize) {
+    self.size
+  }
+
+  fn pump_input(&mut self, inp_man: &mut InputManager) -> Result<()> {
+    for event in self.pending_events.drain(..) {
+      match event {
+        Event::Key(key_event) => inp_man.process_crossterm_key(key_event),
+        Event::Mouse(mouse_event) => inp_man.process_crossterm_mouse(mouse_event, self.offset, self.db_size),
+        _ => (),
+      }
+    }
+
+    Ok(())
+  }
+
+  fn present(&mut self, db: &mut DrawBuffer, offset: (i32, i32)) -> Result<()> {
+    self.offset = offset;
+    self.db_size = (db.width, db.height);
+    db.text_changed = false;
+
+    Ok(())
+  }
+
+  fn syncs_frames(&self) -> bool {
+    false
+  }
+}
+
+/// HeadlessGame. Runs a GameState without a terminal or a window, so the game logic can be driven from tests.
+pub type HeadlessGame<GS> = Game<GS, HeadlessBackend>;
+
+impl<GS> Game<GS, HeadlessBackend>
+where GS: GameState {
+
+  pub fn new(screen_size: (usize, usize)) -> Result<Self> {
+    Self::with_backend(HeadlessBackend::new(screen_size))
+  }
+
+  pub fn inject_key(&mut self, key_event: KeyEvent) -> &mut Self {
+    self.backend().inject_key(key_event);
+
+    self
+  }
+
+  pub fn inject_mouse(&mut self, mouse_event: MouseEvent) -> &mut Self {
+    self.backend().inject_mouse(mouse_event);
+
+    self
+  }
+
+  pub fn press_key(&mut self, key: KeyCode) -> &mut Self {
+    self.inject_key(KeyEvent {
+      code: key,
+      modifiers: KeyModifiers::empty(),
+      kind: KeyEventKind::Press,
+      state: KeyEventState::empty(),
+    })
+  }
+
+  pub fn release_key(&mut self, key: KeyCode) -> &mut Self {
+    self.inject_key(KeyEvent {
+      code: key,
+      modifiers: KeyModifiers::empty(),
+      kind: KeyEventKind::Release,
+      state: KeyEventState::empty(),
+    })
+  }
+
+}
@@ -1,11 +1,11 @@
-use crate::{GameState, InputManager};
+use crate::{GameState, InputManager, Input, normalize_crossterm_key};
 
-use std::collections::HashMap;
-use crossterm::event::KeyCode;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::time::Duration;
 
-type EventFunc<GS> = dyn FnMut(&mut GS);
+type EventFunc<T, C> = dyn FnMut(&mut T, &mut C) -> Propagation;
 
-#[derive(Eq, PartialEq, Hash, Clone)]
+#[derive(Eq, PartialEq, Hash, Clone, Debug)]
 pub enum KeyState {
   Pressed,  // User just pressed the key in this frame.
   Held,     // User is holding the key for more than one frame.
@@ -16,47 +16,585 @@ pub enum KeyState {
   Down, // Pressed or Held or PressedAndReleased
 }
 
-pub struct InputDispatcher<GS> {
-  // A map from a 'Key event' to a function that modifies 'GS', which should be a GameState.
-  bindings: HashMap<(KeyCode, KeyState), Box<EventFunc<GS>>>,
+impl KeyState {
+  /// Whether a key currently in this state fires a binding waiting for 'target'.
+  pub fn triggers(&self, target: &KeyState) -> bool {
+    match target {
+      KeyState::Down => *self != KeyState::Unactive && *self != KeyState::Released,
+      
+      KeyState::Released => *self == KeyState::Released || *self == KeyState::PressedAndReleased,
+      KeyState::Pressed => *self == KeyState::Pressed || *self == KeyState::PressedAndReleased,
+      
+      _ => self == target,
+    }
+  }
+}
+
+/// A key together with the modifiers that must be held with it, e.g. Ctrl+S or Shift+Up.
+/// Shifted characters are stored as their unshifted key plus Shift, so 'W' and '?' become Shift+w and Shift+/.
+#[derive(Eq, PartialEq, Hash, Clone, Copy, Debug)]
+pub struct KeyChord {
+  pub code: KeyCode,
+  pub modifiers: KeyModifiers,
+  pub keypad: bool, // only the keypad's key, e.g. keypad 8 but not the 8 above the letters
+}
+
+impl KeyChord {
+  /// The modifiers a chord can ask for. The rest (Hyper, Meta, ...) are ignored.
+  pub const MODIFIERS: KeyModifiers = KeyModifiers::SHIFT
+    .union(KeyModifiers::CONTROL)
+    .union(KeyModifiers::ALT)
+    .union(KeyModifiers::SUPER);
+
+  pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+    let mut modifiers = modifiers & Self::MODIFIERS;
+
+    // a code that only exists with Shift held implies Shift, even if the terminal didn't report it
+    let unshifted = normalize_crossterm_key(&KeyEvent::new(code, modifiers | KeyModifiers::SHIFT));
+    if unshifted != code {
+      modifiers |= KeyModifiers::SHIFT;
+    }
+
+    Self { code: unshifted, modifiers, keypad: false }
+  }
+
+  /// A key on the keypad. Terminals only tell keypad keys apart if they support the kitty keyboard protocol,
+  /// elsewhere a plain chord of the same key is the portable choice.
+  pub fn on_keypad(code: KeyCode) -> Self {
+    Self { keypad: true, ..Self::new(code, KeyModifiers::empty()) }
+  }
+
+  pub fn ctrl(code: KeyCode) -> Self {
+    Self::new(code, KeyModifiers::CONTROL)
+  }
+
+  pub fn alt(code: KeyCode) -> Self {
+    Self::new(code, KeyModifiers::ALT)
+  }
+
+  pub fn shift(code: KeyCode) -> Self {
+    Self::new(code, KeyModifiers::SHIFT)
+  }
+}
+
+impl From<KeyCode> for KeyChord {
+  fn from(code: KeyCode) -> Self {
+    Self::new(code, KeyModifiers::empty())
+  }
+}
+
+/// What a binding's callback tells the dispatcher about the event it handled.
+#[derive(Eq, PartialEq, Clone, Copy, Debug, Default)]
+pub enum Propagation {
+  /// Lower priority bindings of the same input still fire. Callbacks returning '()' continue.
+  #[default]
+  Continue,
+  /// The event is consumed, no lower priority binding of the same input fires in this frame.
+  Stop,
+}
+
+impl From<()> for Propagation {
+  fn from(_: ()) -> Self {
+    Propagation::Continue
+  }
+}
+
+/// Pace of a repeating binding: it fires when the key goes down, once more after 'delay',
+/// then every 'interval' while the key is held. It runs on the InputManager's game time, so it doesn't depend on the framerate
+/// and replays stay exact. It fires at most once per frame, intervals shorter than a frame are rounded up to it.
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub struct KeyRepeat {
+  pub delay: Duration,
+  pub interval: Duration, // at least a millisecond
+}
+
+impl KeyRepeat {
+  pub fn new(delay: Duration, interval: Duration) -> Self {
+    Self { delay, interval }
+  }
+
+  /// Fires only once per press.
+  pub fn never() -> Self {
+    Self::new(Duration::MAX, Duration::MAX)
+  }
+}
+
+impl Default for KeyRepeat {
+  /// About what a desktop keyboard does.
+  fn default() -> Self {
+    Self::new(Duration::from_millis(250), Duration::from_millis(100))
+  }
+}
+
+/// Handle to a binding, returned by 'bind' so the binding can be removed again.
+#[derive(Eq, PartialEq, Hash, Clone, Copy, Debug)]
+pub struct BindingId(u64);
+
+// What a binding listens to.
+enum Trigger {
+  Key(KeyChord),
+  Action(String),
+}
+
+struct Binding<T, C> {
+  id: BindingId,
+  trigger: Trigger,
+  key_state: KeyState,
+  priority: i32,
+  repeat: Option<KeyRepeat>,
+  next_repeat: Option<Duration>, // game time of the next repeat, None while the key is up
+  callback: Box<EventFunc<T, C>>,
 }
 
-impl<GS> InputDispatcher<GS> {
+impl<T, C> Binding<T, C> {
+  /// Whether a repeating binding fires in the frame at game time 'now'. 'may_repeat' holds back all but the first firing.
+  fn repeats(&mut self, repeat: KeyRepeat, is_down: bool, may_repeat: bool, now: Duration) -> bool {
+    if !is_down {
+      self.next_repeat = None;
+      return false;
+    }
+    
+    let Some(next_repeat) = &mut self.next_repeat else {
+      self.next_repeat = Some(now.saturating_add(repeat.delay));
+      return true;
+    };
+    
+    // the next one counts from now, so repeats missed while the binding wasn't dispatched (its scene was paused) are dropped
+    if *next_repeat <= now && may_repeat {
+      *next_repeat = now.saturating_add(repeat.interval.max(Duration::from_millis(1)));
+      return true;
+    }
+    
+    false
+  }
+}
+
+/// What binding callbacks get next to their target. The dispatcher reads the input through it.
+pub trait InputContext {
+  fn input(&self) -> &InputManager;
+}
+
+impl InputContext for InputManager {
+  fn input(&self) -> &InputManager {
+    self
+  }
+}
+
+/// Bindings from keys and actions to callbacks. A callback gets the target ('T', a GameState or a Scene)
+/// and the context ('C', usually the Engine), so it can change the framerate, read other keys, push scenes and so on.
+pub struct InputDispatcher<T, C = InputManager> {
+  // Sorted by priority and then by the order they were bound in.
+  bindings: Vec<Binding<T, C>>,
+  next_id: u64,
+  
+  // set while the bindings are taken out for dispatching, see 'take'
+  taken: bool,
+  unbound: Vec<BindingId>,
+  cleared: bool, // 'clear' was called, none of the taken bindings come back
+}
+
+impl<T, C> Default for InputDispatcher<T, C> {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl<T, C> InputDispatcher<T, C> {
   pub fn new() -> Self {
     Self {
-      bindings: HashMap::new(),
+      bindings: Vec::new(),
+      next_id: 0,
+      
+      taken: false,
+      unbound: Vec::new(),
+      cleared: false,
     }
   }
   
-  /// Subscribe a function to a key.
-  pub fn bind<F>(&mut self, key: KeyCode, key_state: KeyState, callback: F)
-  where F: FnMut(&mut GS) + 'static {
-    self.bindings.insert((key, key_state), Box::new(callback));
+  /// Subscribe a function to a key, or to a KeyChord. A binding only fires while exactly its modifiers are held,
+  /// so a plain 'S' binding and a Ctrl+S binding never fire together.
+  /// The callback may return a 'Propagation' to consume the event.
+  pub fn bind<K, F, R>(&mut self, key: K, key_state: KeyState, callback: F) -> BindingId
+  where K: Into<KeyChord>, F: FnMut(&mut T, &mut C) -> R + 'static, R: Into<Propagation> {
+    self.bind_with_priority(key, key_state, 0, callback)
   }
   
-  pub fn dispatch(&mut self, manager: &mut InputManager, target: &mut GS) { // TODO need to remove this and use only dispatch_single()
-    for ((key_code, target_state), callback) in &mut self.bindings {
-      let current_state = manager.get_key(*key_code);
+  /// Like 'bind'. Bindings with a higher priority fire first, equal priorities fire in the order they were bound.
+  pub fn bind_with_priority<K, F, R>(&mut self, key: K, key_state: KeyState, priority: i32, callback: F) -> BindingId
+  where K: Into<KeyChord>, F: FnMut(&mut T, &mut C) -> R + 'static, R: Into<Propagation> {
+    self.insert(Trigger::Key(key.into()), key_state, priority, None, callback)
+  }
+  
+  /// Subscribe a function that fires when the key goes down and then repeats at the KeyRepeat's pace while it's held,
+  /// so holding a key gives a steady stream of discrete actions, whatever the framerate.
+  pub fn bind_repeating<K, F, R>(&mut self, key: K, repeat: KeyRepeat, callback: F) -> BindingId
+  where K: Into<KeyChord>, F: FnMut(&mut T, &mut C) -> R + 'static, R: Into<Propagation> {
+    self.insert(Trigger::Key(key.into()), KeyState::Down, 0, Some(repeat), callback)
+  }
+  
+  /// Subscribe a function to a named action. It fires through any of the action's inputs.
+  pub fn bind_action<F, R>(&mut self, action: &str, key_state: KeyState, callback: F) -> BindingId
+  where F: FnMut(&mut T, &mut C) -> R + 'static, R: Into<Propagation> {
+    self.bind_action_with_priority(action, key_state, 0, callback)
+  }
+  
+  pub fn bind_action_with_priority<F, R>(&mut self, action: &str, key_state: KeyState, priority: i32, callback: F) -> BindingId
+  where F: FnMut(&mut T, &mut C) -> R + 'static, R: Into<Propagation> {
+    self.insert(Trigger::Action(action.to_string()), key_state, priority, None, callback)
+  }
+  
+  pub fn bind_action_repeating<F, R>(&mut self, action: &str, repeat: KeyRepeat, callback: F) -> BindingId
+  where F: FnMut(&mut T, &mut C) -> R + 'static, R: Into<Propagation> {
+    self.insert(Trigger::Action(action.to_string()), KeyState::Down, 0, Some(repeat), callback)
+  }
+  
+  fn insert<F, R>(&mut self, trigger: Trigger, key_state: KeyState, priority: i32, repeat: Option<KeyRepeat>, mut callback: F) -> BindingId
+  where F: FnMut(&mut T, &mut C) -> R + 'static, R: Into<Propagation> {
+    let id = BindingId(self.next_id);
+    self.next_id += 1;
+    
+    self.insert_binding(Binding {
+      id,
+      trigger,
+      key_state,
+      priority,
+      repeat,
+      next_repeat: None,
+      callback: Box::new(move |target, ctx| callback(target, ctx).into()),
+    });
+    
+    id
+  }
+  
+  fn insert_binding(&mut self, binding: Binding<T, C>) {
+    // after every binding of the same priority, so ties keep their binding order
+    let index = self.bindings.partition_point(|bound| bound.priority >= binding.priority);
+    self.bindings.insert(index, binding);
+  }
+  
+  /// Removes a binding. Returns false if it was already removed.
+  /// While the bindings are taken out for dispatching, the removal waits for 'restore' and this returns true.
+  pub fn unbind(&mut self, id: BindingId) -> bool {
+    let len = self.bindings.len();
+    self.bindings.retain(|binding| binding.id != id);
+    
+    if self.taken && self.bindings.len() == len {
+      self.unbound.push(id);
+      return true;
+    }
+    
+    self.bindings.len() != len
+  }
+  
+  /// Removes every binding. While the bindings are taken out, this includes the taken ones,
+  /// but not the ones bound after the call.
+  pub fn clear(&mut self) {
+    if self.taken {
+      self.cleared = true;
+      self.unbound.clear();
+    }
+    self.bindings.clear();
+  }
+  
+  /// Moves the bindings out, so they can be dispatched with a context that owns this dispatcher (like the Engine).
+  /// Bindings added or removed in the meantime are merged back by 'restore'.
+  pub fn take(&mut self) -> Self {
+    self.taken = true;
+    
+    Self {
+      bindings: std::mem::take(&mut self.bindings),
+      next_id: self.next_id,
+      
+      taken: false,
+      unbound: Vec::new(),
+      cleared: false,
+    }
+  }
+  
+  pub fn restore(&mut self, mut taken: Self) {
+    if self.cleared {
+      taken.bindings.clear();
+    }
+    taken.bindings.retain(|binding| !self.unbound.contains(&binding.id));
+    for binding in std::mem::take(&mut self.bindings) {
+      taken.insert_binding(binding);
+    }
+    taken.next_id = self.next_id;
+    
+    *self = taken;
+  }
+}
+
+impl<T, C> InputDispatcher<T, C>
+where C: InputContext {
+  pub fn dispatch(&mut self, target: &mut T, ctx: &mut C) {
+    let mut consumed: Vec<Input> = Vec::new();
+    
+    for binding in &mut self.bindings {
+      let manager = ctx.input();
       
-      let is_triggered = match target_state {
-        KeyState::Down => current_state != KeyState::Unactive && current_state != KeyState::Released,
-        
-        KeyState::Released => current_state == KeyState::Released || current_state == KeyState::PressedAndReleased,
-        KeyState::Pressed => current_state == KeyState::Pressed || current_state == KeyState::PressedAndReleased,
-        
-        _ => current_state == *target_state,
+      // the inputs that make this binding fire right now
+      let firing: Vec<Input> = match &binding.trigger {
+        Trigger::Key(chord) => vec![Input::Key(*chord)],
+        Trigger::Action(action) => manager.actions.inputs(action).to_vec(),
       };
-        
-      if is_triggered {
-        callback(target);
+      let firing: Vec<Input> = firing.into_iter()
+        .filter(|input| manager.input_state(input).triggers(&binding.key_state))
+        .filter(|input| !consumed.contains(input))
+        .collect();
+      
+      let mut is_triggered = !firing.is_empty();
+      if let Trigger::Action(action) = &binding.trigger {
+        is_triggered = is_triggered && manager.action_state(action).triggers(&binding.key_state);
+      }
+      
+      let fires = match binding.repeat {
+        None => is_triggered,
+        Some(repeat) => {
+          let may_repeat = firing.iter().any(|input| manager.is_repeating(input));
+          binding.repeats(repeat, is_triggered, may_repeat, manager.time)
+        },
+      };
+      
+      if fires && (binding.callback)(target, ctx) == Propagation::Stop {
+        consumed.extend(firing.iter().copied());
       }
     }
   }
   
-  pub fn dispatch_single(&mut self, key_code: KeyCode, _manager: &mut InputManager, target: &mut GS) {
-    if let Some(callback) = self.bindings.get_mut(&(key_code, KeyState::Pressed)) {
-      callback(target);
+  /// Fires the Pressed bindings of a single key, whatever the input's state.
+  pub fn dispatch_single<K>(&mut self, key: K, target: &mut T, ctx: &mut C)
+  where K: Into<KeyChord> {
+    let chord = key.into();
+    
+    for binding in &mut self.bindings {
+      let Trigger::Key(bound) = &binding.trigger else { continue; };
+      
+      if *bound == chord && binding.key_state == KeyState::Pressed && (binding.callback)(target, ctx) == Propagation::Stop {
+        break;
+      }
     }
   }
   
 }
+
+#[cfg(test)]
+mod tests {
+  use crate::{Engine, EngineConfig, Game, GameState, HeadlessBackend, HeadlessGame, KeyState, KeyRepeat, KeyReleaseMode, InputDispatcher, Scene, SceneCommand};
+
+  use crossterm::event::KeyCode;
+  use std::cell::RefCell;
+  use std::rc::Rc;
+  use std::time::Duration;
+
+  #[derive(Default)]
+  struct Counter {
+    old: u32,
+    new: u32,
+  }
+
+  impl GameState for Counter {
+    fn new(ctx: &mut Engine<Self>) -> Self {
+      ctx.bind(KeyCode::Char('o'), KeyState::Pressed, |gs, _ctx| { gs.old += 1; });
+      ctx.bind(KeyCode::Char('c'), KeyState::Pressed, |_gs, ctx| {
+        ctx.inp_dis.clear();
+        ctx.bind(KeyCode::Char('n'), KeyState::Pressed, |gs, _ctx| { gs.new += 1; });
+      });
+
+      Self::default()
+    }
+
+    fn update(&mut self, _ctx: &mut Engine<Self>) {}
+
+    fn draw(&mut self, _ctx: &mut Engine<Self>) {}
+
+    fn should_run(&mut self) -> bool {
+      true
+    }
+  }
+
+  fn tap(game: &mut HeadlessGame<Counter>, key: KeyCode) {
+    game.press_key(key);
+    game.step().unwrap();
+    game.release_key(key);
+    game.step().unwrap();
+  }
+
+  #[test]
+  fn clear_from_a_handler_drops_the_taken_bindings() {
+    let mut game = HeadlessGame::<Counter>::new((10, 10)).unwrap();
+
+    tap(&mut game, KeyCode::Char('o'));
+    assert_eq!(game.game_state().old, 1);
+
+    tap(&mut game, KeyCode::Char('c'));
+    tap(&mut game, KeyCode::Char('o'));
+    tap(&mut game, KeyCode::Char('n'));
+
+    assert_eq!(game.game_state().old, 1, "bindings from before the clear are gone");
+    assert_eq!(game.game_state().new, 1, "bindings made after the clear are kept");
+  }
+
+  // game time in milliseconds of every firing, per key
+  #[derive(Default)]
+  struct Repeats {
+    r: Vec<u128>,
+    x: Vec<u128>,
+    w: Rc<RefCell<Vec<u128>>>, // shared with the Walking scene
+  }
+
+  impl GameState for Repeats {
+    fn new(ctx: &mut Engine<Self>) -> Self {
+      ctx.fixed_time_step = Duration::from_millis(10);
+
+      let repeat = KeyRepeat::new(Duration::from_millis(50), Duration::from_millis(20));
+      ctx.bind_repeating(KeyCode::Char('r'), repeat, |gs: &mut Repeats, ctx| { gs.r.push(ctx.inp_man.time.as_millis()); });
+      ctx.bind_repeating(KeyCode::Char('x'), KeyRepeat::never(), |gs: &mut Repeats, ctx| { gs.x.push(ctx.inp_man.time.as_millis()); });
+      let gs = Self::default();
+      ctx.push_scene(Walking(gs.w.clone()));
+
+      gs
+    }
+
+    fn update(&mut self, _ctx: &mut Engine<Self>) {}
+
+    fn draw(&mut self, _ctx: &mut Engine<Self>) {}
+
+    fn should_run(&mut self) -> bool {
+      true
+    }
+  }
+
+  // repeats 'w' while it's the top scene
+  struct Walking(Rc<RefCell<Vec<u128>>>);
+
+  impl Scene<Repeats> for Walking {
+    fn on_enter(&mut self, bindings: &mut InputDispatcher<Self, Engine<Repeats>>, _gs: &mut Repeats, _ctx: &mut Engine<Repeats>) {
+      bindings.bind_repeating(KeyCode::Char('w'), KeyRepeat::default(), |scene: &mut Walking, ctx| {
+        scene.0.borrow_mut().push(ctx.inp_man.time.as_millis());
+      });
+    }
+
+    fn update(&mut self, _gs: &mut Repeats, _ctx: &mut Engine<Repeats>) -> SceneCommand<Repeats> {
+      SceneCommand::None
+    }
+
+    fn draw(&mut self, _gs: &mut Repeats, _ctx: &mut Engine<Repeats>) {}
+  }
+
+  // a popup without bindings of its own
+  struct Popup;
+
+  impl Scene<Repeats> for Popup {
+    fn update(&mut self, _gs: &mut Repeats, _ctx: &mut Engine<Repeats>) -> SceneCommand<Repeats> {
+      SceneCommand::None
+    }
+
+    fn draw(&mut self, _gs: &mut Repeats, _ctx: &mut Engine<Repeats>) {}
+  }
+
+  #[test]
+  fn repeats_wait_for_the_delay_then_keep_the_interval() {
+    let mut game = HeadlessGame::<Repeats>::new((10, 10)).unwrap();
+
+    game.press_key(KeyCode::Char('r'));
+    game.step_n(12).unwrap(); // 0 to 110ms
+
+    assert_eq!(game.game_state().r, [0, 50, 70, 90, 110]);
+  }
+
+  #[test]
+  fn releasing_starts_the_delay_over() {
+    let mut game = HeadlessGame::<Repeats>::new((10, 10)).unwrap();
+
+    game.press_key(KeyCode::Char('r'));
+    game.step_n(6).unwrap(); // 0 to 50ms
+    game.release_key(KeyCode::Char('r'));
+    game.step_n(2).unwrap(); // 60, 70ms
+    game.press_key(KeyCode::Char('r'));
+    game.step_n(7).unwrap(); // 80 to 140ms
+
+    assert_eq!(game.game_state().r, [0, 50, 80, 130]);
+  }
+
+  #[test]
+  fn never_fires_once_per_press() {
+    let mut game = HeadlessGame::<Repeats>::new((10, 10)).unwrap();
+
+    game.press_key(KeyCode::Char('x'));
+    game.step_n(100).unwrap();
+    game.release_key(KeyCode::Char('x'));
+    game.step().unwrap();
+    game.press_key(KeyCode::Char('x'));
+    game.step_n(100).unwrap();
+
+    assert_eq!(game.game_state().x, [0, 1010]);
+  }
+
+  #[test]
+  fn repeats_missed_while_paused_are_dropped() {
+    let mut game = HeadlessGame::<Repeats>::new((10, 10)).unwrap();
+
+    game.press_key(KeyCode::Char('w'));
+    game.step_n(30).unwrap(); // 0 to 290ms
+    assert_eq!(*game.game_state().w.borrow(), [0, 250]);
+
+    // 'w' stays held for 5 seconds behind a popup
+    game.engine().push_scene(Popup);
+    game.step_n(500).unwrap();
+    game.engine().pop_scene();
+    game.step().unwrap();
+    assert_eq!(*game.game_state().w.borrow(), [0, 250, 5300]);
+
+    // and goes on at its pace from there
+    game.step_n(20).unwrap();
+    assert_eq!(*game.game_state().w.borrow(), [0, 250, 5300, 5400, 5500]);
+  }
+
+  fn synthesized_game(config: EngineConfig) -> HeadlessGame<Repeats> {
+    let mut game: HeadlessGame<Repeats> = Game::with_config(HeadlessBackend::new((10, 10)), config).unwrap();
+    game.engine().inp_man.set_key_release_mode(KeyReleaseMode::Synthesized);
+
+    game
+  }
+
+  #[test]
+  fn a_synthesized_tap_fires_once() {
+    let mut game = synthesized_game(EngineConfig::default());
+
+    // no release comes, the key looks held until the release timeout
+    game.press_key(KeyCode::Char('r'));
+    game.step_n(100).unwrap();
+
+    assert_eq!(game.game_state().r, [0]);
+  }
+
+  #[test]
+  fn synthesized_repeats_wait_for_the_auto_repeat() {
+    let mut game = synthesized_game(EngineConfig::default());
+
+    // the system waits 300ms, then repeats every 30ms
+    game.press_key(KeyCode::Char('r'));
+    game.step_n(30).unwrap();
+    for _ in 0..4 {
+      game.press_key(KeyCode::Char('r'));
+      game.step_n(3).unwrap();
+    }
+
+    assert_eq!(game.game_state().r, [0, 300, 320, 340, 360, 380, 400]);
+  }
+
+  #[test]
+  fn the_release_timeout_can_be_configured() {
+    let config = EngineConfig { release_timeout: Some(Duration::from_millis(200)), ..Default::default() };
+    let mut game = synthesized_game(config);
+    assert_eq!(game.engine().inp_man.release_timeout, Duration::from_millis(200));
+
+    game.press_key(KeyCode::Char('x'));
+    game.step_n(20).unwrap(); // 0 to 190ms
+    assert_eq!(game.engine().inp_man.get_key(KeyCode::Char('x')), KeyState::Held);
+
+    game.step().unwrap();
+    assert_eq!(game.engine().inp_man.get_key(KeyCode::Char('x')), KeyState::Unactive);
+  }
+}
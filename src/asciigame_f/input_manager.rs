@@ -1,18 +1,74 @@
-use crate::{InputDispatcher, KeyState};
+use crate::{InputDispatcher, KeyState, KeyChord, ActionMap, Input, TextEvent};
 
 use crossterm::event::{poll, read, Event};
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use anyhow::Result;
 
+/// Where key releases come from.
+#[derive(Eq, PartialEq, Clone, Copy, Debug, Default)]
+pub enum KeyReleaseMode {
+  /// The platform reports releases (winit, terminals with the kitty keyboard protocol).
+  #[default]
+  Reported,
+  /// The platform only reports presses and their auto-repeats, so a key counts as released once neither came
+  /// for 'InputManager::release_timeout'. The timeout should outlast the pause before the auto-repeat starts
+  /// (250 to 600ms, depending on the system), or a held key is let go once before its repeats arrive.
+  /// The longer it is, the later releases are noticed, so a tap looks held for that long; repeating bindings
+  /// wait for the platform's auto-repeat before they repeat (see 'InputManager::is_repeating').
+  Synthesized,
+}
+
 pub struct InputManager {
   pub key_events: HashMap<crossterm::event::KeyCode, crate::KeyState>,
+  pub keypad_keys: HashMap<crossterm::event::KeyCode, crate::KeyState>, // the keys of 'key_events' pressed on the keypad
+  pub modifiers: crossterm::event::KeyModifiers, // held as of the last key event, only the ones in 'KeyChord::MODIFIERS'
+  
+  pub mouse_buttons: HashMap<crossterm::event::MouseButton, crate::KeyState>,
+  pub mouse_position: Option<(i32, i32)>, // in DrawBuffer cells, None when the cursor isn't over the DrawBuffer
+  pub wheel: (i32, i32), // (horizontal, vertical) scroll of this frame. Positive is right and down
+  
+  pub actions: ActionMap,
+  
+  pub time: std::time::Duration, // game time of the current frame, advanced by the Engine one fixed time step per frame
+  
+  text_input: bool,
+  pub text_events: Vec<TextEvent>, // typed in this frame, only while text input is on
+  
+  release_mode: KeyReleaseMode,
+  pub release_timeout: std::time::Duration, // see 'KeyReleaseMode::Synthesized', longer than the auto-repeat interval
+  last_key_event: HashMap<crossterm::event::KeyCode, std::time::Duration>, // game time of each down key's last press or repeat
+  auto_repeating: HashSet<crossterm::event::KeyCode>, // down keys the platform sent a repeat for
+}
+
+impl Default for InputManager {
+  fn default() -> Self {
+    Self::new()
+  }
 }
 
 impl InputManager {
   pub fn new() -> InputManager {
     InputManager {
       key_events: Default::default(),
+      keypad_keys: Default::default(),
+      modifiers: crossterm::event::KeyModifiers::empty(),
+      
+      mouse_buttons: Default::default(),
+      mouse_position: None,
+      wheel: (0, 0),
+      
+      actions: ActionMap::new(),
+      
+      time: std::time::Duration::ZERO,
+      
+      text_input: false,
+      text_events: Vec::new(),
+      
+      release_mode: KeyReleaseMode::Reported,
+      release_timeout: std::time::Duration::from_millis(500), // outlasts the auto-repeat delay of most systems
+      last_key_event: HashMap::new(),
+      auto_repeating: HashSet::new(),
     }
   }
 
@@ -20,10 +76,66 @@ impl InputManager {
     self.key_events.entry(key).or_insert(KeyState::Unactive).clone()
   }
   
+  pub fn get_mouse_button(&mut self, button: crossterm::event::MouseButton) -> KeyState {
+    self.mouse_buttons.entry(button).or_insert(KeyState::Unactive).clone()
+  }
+  
+  /// State of a single input. Keys only count while exactly their chord's modifiers are held.
+  pub fn input_state(&self, input: &Input) -> KeyState {
+    let state = match input {
+      Input::Key(chord) if chord.modifiers != self.modifiers => None,
+      Input::Key(chord) if chord.keypad => self.keypad_keys.get(&chord.code),
+      Input::Key(chord) => self.key_events.get(&chord.code),
+      Input::Mouse(button) => self.mouse_buttons.get(button),
+    };
+    
+    state.cloned().unwrap_or(KeyState::Unactive)
+  }
+  
+  /// State of a named action, merged from all of its inputs. Holding any of them keeps the action held,
+  /// so switching from one key to another doesn't release and press it again.
+  pub fn action_state(&self, action: &str) -> KeyState {
+    let states: Vec<KeyState> = self.actions.inputs(action).iter().map(|input| self.input_state(input)).collect();
+    
+    [KeyState::Held, KeyState::Pressed, KeyState::PressedAndReleased, KeyState::Released].into_iter()
+      .find(|state| states.contains(state))
+      .unwrap_or(KeyState::Unactive)
+  }
+  
+  /// Every key and mouse button pressed in this frame, with the modifiers held. Meant for "press a key" rebinding menus.
+  pub fn pressed_inputs(&self) -> Vec<Input> {
+    let pressed = |state: &KeyState| state.triggers(&KeyState::Pressed);
+    
+    let keypad_keys = self.keypad_keys.iter()
+      .filter(|(_, state)| pressed(state))
+      .map(|(code, _)| Input::Key(KeyChord { keypad: true, ..KeyChord::new(*code, self.modifiers) }));
+    let keys = self.key_events.iter()
+      .filter(|(code, state)| pressed(state) && !self.keypad_keys.get(code).is_some_and(pressed))
+      .map(|(code, _)| Input::Key(KeyChord::new(*code, self.modifiers)));
+    let buttons = self.mouse_buttons.iter()
+      .filter(|(_, state)| pressed(state))
+      .map(|(button, _)| Input::Mouse(*button));
+    
+    keypad_keys.chain(keys).chain(buttons).collect()
+  }
+  
+  /// True on the frame any of the action's inputs was pressed, unless another one was already held.
+  pub fn action_pressed(&self, action: &str) -> bool {
+    self.action_state(action).triggers(&KeyState::Pressed)
+  }
+  
+  pub fn action_down(&self, action: &str) -> bool {
+    self.action_state(action).triggers(&KeyState::Down)
+  }
+  
+  pub fn action_released(&self, action: &str) -> bool {
+    self.action_state(action).triggers(&KeyState::Released)
+  }
+  
   /// should be called at the end of a frame
   pub fn cycle_events(&mut self) {
   
-    for state in &mut self.key_events.values_mut() {
+    for state in self.key_events.values_mut().chain(self.keypad_keys.values_mut()).chain(self.mouse_buttons.values_mut()) {
       if *state == KeyState::Pressed {
         *state = KeyState::Held;
       } else if *state == KeyState::Released || *state == KeyState::PressedAndReleased {
@@ -31,73 +143,362 @@ impl InputManager {
       }
     }
     
+    self.wheel = (0, 0);
+    self.text_events.clear();
+    
+  }
+  
+  pub fn key_release_mode(&self) -> KeyReleaseMode {
+    self.release_mode
+  }
+  
+  /// Set by the Engine from 'Backend::key_release_mode' when the game starts. Games may still override it.
+  pub fn set_key_release_mode(&mut self, mode: KeyReleaseMode) {
+    self.release_mode = mode;
+    self.last_key_event.clear();
+    self.auto_repeating.clear();
+  }
+  
+  /// Whether a down input may repeat. In the Synthesized mode a key that was only tapped looks held until the release timeout,
+  /// so it only counts once the platform's auto-repeat arrived for it. Always true otherwise.
+  pub fn is_repeating(&self, input: &Input) -> bool {
+    match input {
+      Input::Key(chord) if self.release_mode == KeyReleaseMode::Synthesized => self.auto_repeating.contains(&chord.code),
+      _ => true,
+    }
+  }
+  
+  /// In the Synthesized mode, releases the keys that went quiet. Called by the Engine right after the input was pumped.
+  pub fn synthesize_releases(&mut self) {
+    use crossterm::event::KeyEventKind;
+    
+    if self.release_mode != KeyReleaseMode::Synthesized { return; }
+    
+    let (now, timeout) = (self.time, self.release_timeout);
+    let quiet: Vec<crossterm::event::KeyCode> = self.last_key_event.iter()
+      .filter(|(_, last)| now.saturating_sub(**last) >= timeout)
+      .map(|(code, _)| *code)
+      .collect();
+    
+    for code in quiet {
+      self.last_key_event.remove(&code);
+      self.auto_repeating.remove(&code);
+      
+      for states in [&mut self.key_events, &mut self.keypad_keys] {
+        if let Some(state) = states.get_mut(&code) && (*state == KeyState::Pressed || *state == KeyState::Held) {
+          apply_event_kind(state, KeyEventKind::Release);
+        }
+      }
+    }
+    
+    // modifiers only arrive along with keys, so once every key went quiet nothing can still be holding them
+    if self.last_key_event.is_empty() {
+      self.modifiers = crossterm::event::KeyModifiers::empty();
+    }
+  }
+  
+  /// Switches to text input mode: typed characters and editing keys go to 'text_events' instead of the key states,
+  /// so typing a name doesn't also move the player. Keys that don't type or edit anything still reach the bindings.
+  pub fn start_text_input(&mut self) {
+    self.text_input = true;
+  }
+  
+  pub fn stop_text_input(&mut self) {
+    self.text_input = false;
+  }
+  
+  pub fn is_text_input(&self) -> bool {
+    self.text_input
+  }
+  
+  /// Inserts text directly, e.g. a paste or an IME commit. Ignored outside of text input mode.
+  pub fn insert_text(&mut self, text: &str) {
+    if self.text_input && !text.is_empty() {
+      self.text_events.push(TextEvent::Insert(text.to_string()));
+    }
+  }
+  
+  /// In text input mode, turns a key event into a TextEvent. Returns false if the key should go to the key states instead.
+  fn process_text_key(&mut self, key_event: &crossterm::event::KeyEvent, text_event: Option<TextEvent>) -> bool {
+    use crossterm::event::KeyEventKind;
+    
+    if !self.text_input { return false; }
+    let Some(text_event) = text_event else { return false; };
+    
+    if key_event.kind == KeyEventKind::Release {
+      // the press was turned into text, unless the key was already down when text input started
+      let chord = KeyChord::new(key_event.code, key_event.modifiers);
+      return self.key_events.get(&chord.code).is_none_or(|state| *state == KeyState::Unactive);
+    }
+    
+    if text_event != TextEvent::Insert(String::new()) { // dead keys type nothing until they're composed
+      self.text_events.push(text_event);
+    }
+    
+    true
   }
   
   pub fn process_crossterm_key(&mut self, key_event: crossterm::event::KeyEvent) {
   
     // println!("{:?}, {:?}", key_event.code, key_event.modifiers);
 
-    let event_code = normalize_crossterm_key(&key_event);
+    if self.process_text_key(&key_event, crossterm_text_event(&key_event)) { return; }
+    
+    self.process_key(key_event);
+  }
+  
+  /// Applies a key event to the key states, after text input had its chance at it.
+  fn process_key(&mut self, key_event: crossterm::event::KeyEvent) {
+    let chord = KeyChord::new(key_event.code, key_event.modifiers);
+    self.modifiers = chord.modifiers;
 
-    let current_state = self.key_events.entry(event_code).or_insert(KeyState::Unactive);
+    let current_state = self.key_events.entry(chord.code).or_insert(KeyState::Unactive);
+    apply_event_kind(current_state, key_event.kind);
     
-    match key_event.kind {
-      crossterm::event::KeyEventKind::Press => {
-        if *current_state == KeyState::Unactive || *current_state == KeyState::Released {
-          *current_state = KeyState::Pressed;
-        }
-      },
-      crossterm::event::KeyEventKind::Repeat => { *current_state = KeyState::Held; },
-      crossterm::event::KeyEventKind::Release => {
-        if *current_state == KeyState::Pressed {
-          *current_state = KeyState::PressedAndReleased;
-        } else {
-          *current_state = KeyState::Released;
-        }
-      },
+    if self.release_mode == KeyReleaseMode::Synthesized {
+      // without the kitty protocol, auto-repeats arrive as presses too
+      match key_event.kind {
+        crossterm::event::KeyEventKind::Release => {
+          self.last_key_event.remove(&chord.code);
+          self.auto_repeating.remove(&chord.code);
+        },
+        _ => if self.last_key_event.insert(chord.code, self.time).is_some() {
+          self.auto_repeating.insert(chord.code);
+        },
+      };
+    }
+    
+    if key_event.state.contains(crossterm::event::KeyEventState::KEYPAD) {
+      let current_state = self.keypad_keys.entry(chord.code).or_insert(KeyState::Unactive);
+      apply_event_kind(current_state, key_event.kind);
     }
     
   }
   
-  pub fn process_winit_key(&mut self, key_event: winit::event::KeyEvent) {
-    use winit::keyboard::{Key, NamedKey};
-    use crossterm::event::KeyCode as CKeyCode; // Crossterm
-
-    let code = if let winit::keyboard::PhysicalKey::Code(keycode) = key_event.physical_key {
-      translate_winit_physical(keycode)
-    } else {
-      CKeyCode::Null
+  pub fn process_mouse_button(&mut self, button: crossterm::event::MouseButton, kind: crossterm::event::KeyEventKind) {
+    let current_state = self.mouse_buttons.entry(button).or_insert(KeyState::Unactive);
+    
+    apply_event_kind(current_state, kind);
+  }
+  
+  pub fn scroll(&mut self, horizontal: i32, vertical: i32) {
+    self.wheel.0 += horizontal;
+    self.wheel.1 += vertical;
+  }
+  
+  /// 'offset' is where the DrawBuffer's top-left cell is on the terminal (see 'Engine::screen_offset').
+  pub fn process_crossterm_mouse(&mut self, mouse_event: crossterm::event::MouseEvent, offset: (i32, i32), db_size: (usize, usize)) {
+    use crossterm::event::{MouseEventKind, KeyEventKind};
+    
+    let (col, row) = (mouse_event.column as i32 - offset.0, mouse_event.row as i32 - offset.1);
+    let inside = col >= 0 && row >= 0 && (col as usize) < db_size.0 && (row as usize) < db_size.1;
+    self.mouse_position = if inside { Some((col, row)) } else { None };
+    
+    match mouse_event.kind {
+      MouseEventKind::Down(button) => self.process_mouse_button(button, KeyEventKind::Press),
+      MouseEventKind::Up(button) => self.process_mouse_button(button, KeyEventKind::Release),
+      MouseEventKind::ScrollUp => self.scroll(0, -1),
+      MouseEventKind::ScrollDown => self.scroll(0, 1),
+      MouseEventKind::ScrollLeft => self.scroll(-1, 0),
+      MouseEventKind::ScrollRight => self.scroll(1, 0),
+      MouseEventKind::Drag(_) | MouseEventKind::Moved => (),
+    }
+  }
+  
+  pub fn process_winit_mouse_button(&mut self, button: winit::event::MouseButton, state: winit::event::ElementState) {
+    use crossterm::event::{MouseButton as CButton, KeyEventKind};
+    use winit::event::{MouseButton as WButton, ElementState};
+    
+    let button = match button {
+      WButton::Left => CButton::Left,
+      WButton::Right => CButton::Right,
+      WButton::Middle => CButton::Middle,
+      _ => return, // crossterm has nothing for the back/forward/other buttons
     };
-
-    let modifiers = crossterm::event::KeyModifiers::empty(); // shift, control, alt, etc. TODO
-
-    let kind = match key_event.state { // press, release or repeat
-      winit::event::ElementState::Pressed => {
-        match key_event.repeat {
-          false => crossterm::event::KeyEventKind::Press,
-          true => crossterm::event::KeyEventKind::Repeat,
-        }
-      },
-      winit::event::ElementState::Released => crossterm::event::KeyEventKind::Release,
+    
+    let kind = match state {
+      ElementState::Pressed => KeyEventKind::Press,
+      ElementState::Released => KeyEventKind::Release,
     };
     
-    let state = crossterm::event::KeyEventState::empty(); // signifies capslock, numlock, keypad, etc.
+    self.process_mouse_button(button, kind);
+  }
+  
+  /// 'modifiers' are the ones winit reported (through 'WindowEvent::ModifiersChanged') when the key event arrived.
+  pub fn process_winit_key(&mut self, key_event: winit::event::KeyEvent, modifiers: winit::keyboard::ModifiersState) {
+    let translated = translate_winit_event(
+      key_event.physical_key, &key_event.logical_key, key_event.location, key_event.state, key_event.repeat, modifiers,
+    );
     
-    let translated = crossterm::event::KeyEvent{
-      code,
-      modifiers,
-      kind,
-      state,
-    };
+    if self.process_text_key(&translated, winit_text_event(&key_event, translated.modifiers)) { return; }
     
-    self.process_crossterm_key(translated);
+    self.process_key(translated);
     
   }
   
 }
 
+/// The TextEvent a crossterm key types or edits with, if any. Crossterm already reports the shifted character.
+fn crossterm_text_event(key_event: &crossterm::event::KeyEvent) -> Option<TextEvent> {
+  use crossterm::event::{KeyCode, KeyModifiers};
+  
+  if key_event.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SUPER) {
+    return None;
+  }
+  
+  editing_text_event(key_event.code).or(match key_event.code {
+    KeyCode::Char(c) => Some(TextEvent::Insert(c.to_string())),
+    _ => None,
+  })
+}
+
+/// The TextEvent a winit key types or edits with, if any. The typed text comes from winit, so it's shifted and composed.
+fn winit_text_event(key_event: &winit::event::KeyEvent, modifiers: crossterm::event::KeyModifiers) -> Option<TextEvent> {
+  use crossterm::event::{KeyCode, KeyModifiers};
+  use winit::keyboard::{Key, NamedKey};
+  
+  if modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SUPER) {
+    return None;
+  }
+  
+  let editing = match &key_event.logical_key {
+    Key::Named(NamedKey::Backspace) => KeyCode::Backspace,
+    Key::Named(NamedKey::Delete) => KeyCode::Delete,
+    Key::Named(NamedKey::ArrowLeft) => KeyCode::Left,
+    Key::Named(NamedKey::ArrowRight) => KeyCode::Right,
+    Key::Named(NamedKey::Home) => KeyCode::Home,
+    Key::Named(NamedKey::End) => KeyCode::End,
+    Key::Named(NamedKey::Enter) => KeyCode::Enter,
+    Key::Named(NamedKey::Escape) => KeyCode::Esc,
+    _ => KeyCode::Null,
+  };
+  if let Some(text_event) = editing_text_event(editing) {
+    return Some(text_event);
+  }
+  
+  // releases carry no text, but the logical key still tells whether the press typed something
+  let text = match &key_event.logical_key {
+    Key::Character(character) => key_event.text.as_ref().unwrap_or(character).to_string(),
+    Key::Named(NamedKey::Space) => " ".to_string(),
+    Key::Dead(_) => String::new(),
+    _ => key_event.text.as_ref()?.to_string(),
+  };
+  
+  (!text.chars().any(char::is_control)).then_some(TextEvent::Insert(text))
+}
+
+fn editing_text_event(code: crossterm::event::KeyCode) -> Option<TextEvent> {
+  use crossterm::event::KeyCode;
+  
+  Some(match code {
+    KeyCode::Backspace => TextEvent::Backspace,
+    KeyCode::Delete => TextEvent::Delete,
+    KeyCode::Left => TextEvent::Left,
+    KeyCode::Right => TextEvent::Right,
+    KeyCode::Home => TextEvent::Home,
+    KeyCode::End => TextEvent::End,
+    KeyCode::Enter => TextEvent::Submit,
+    KeyCode::Esc => TextEvent::Cancel,
+    _ => return None,
+  })
+}
+
+/// Moves a key or button state along after a press, repeat or release.
+fn apply_event_kind(current_state: &mut KeyState, kind: crossterm::event::KeyEventKind) {
+  match kind {
+    crossterm::event::KeyEventKind::Press => {
+      if *current_state == KeyState::Unactive || *current_state == KeyState::Released {
+        *current_state = KeyState::Pressed;
+      }
+    },
+    crossterm::event::KeyEventKind::Repeat => { *current_state = KeyState::Held; },
+    crossterm::event::KeyEventKind::Release => {
+      if *current_state == KeyState::Pressed {
+        *current_state = KeyState::PressedAndReleased;
+      } else {
+        *current_state = KeyState::Released;
+      }
+    },
+  }
+}
+
 use winit::keyboard::KeyCode as WCode;
-use crossterm::event::{KeyCode as CCode, ModifierKeyCode};
+use crossterm::event::{KeyCode as CCode, ModifierKeyCode, MediaKeyCode};
+
+/// Translates a winit key to the crossterm key it would be in a terminal, and whether it's on the keypad.
+/// With NumLock off the keypad digits are arrows, Home, PageUp and so on, as they are in terminals.
+pub fn translate_winit_key(key_event: &winit::event::KeyEvent) -> (CCode, bool) {
+  translate_winit_key_parts(key_event.physical_key, &key_event.logical_key, key_event.location)
+}
+
+/// 'translate_winit_key' on the parts of a KeyEvent, which can't be built outside of winit.
+fn translate_winit_key_parts(
+  physical_key: winit::keyboard::PhysicalKey,
+  logical_key: &winit::keyboard::Key,
+  location: winit::keyboard::KeyLocation,
+) -> (CCode, bool) {
+  use winit::keyboard::{Key, KeyLocation, NamedKey, PhysicalKey};
+  
+  let PhysicalKey::Code(w_code) = physical_key else {
+    return (CCode::Null, false);
+  };
+  let keypad = location == KeyLocation::Numpad;
+  
+  if keypad && let Key::Named(named) = logical_key {
+    let navigation = match named {
+      NamedKey::ArrowUp => Some(CCode::Up),
+      NamedKey::ArrowDown => Some(CCode::Down),
+      NamedKey::ArrowLeft => Some(CCode::Left),
+      NamedKey::ArrowRight => Some(CCode::Right),
+      NamedKey::Home => Some(CCode::Home),
+      NamedKey::End => Some(CCode::End),
+      NamedKey::PageUp => Some(CCode::PageUp),
+      NamedKey::PageDown => Some(CCode::PageDown),
+      NamedKey::Insert => Some(CCode::Insert),
+      NamedKey::Delete => Some(CCode::Delete),
+      NamedKey::Clear => Some(CCode::KeypadBegin),
+      _ => None,
+    };
+    
+    if let Some(code) = navigation {
+      return (code, true);
+    }
+  }
+  
+  (translate_winit_physical(w_code), keypad)
+}
+
+/// The crossterm event a winit key event would have been in a terminal, so both go through the same key handling.
+fn translate_winit_event(
+  physical_key: winit::keyboard::PhysicalKey,
+  logical_key: &winit::keyboard::Key,
+  location: winit::keyboard::KeyLocation,
+  element_state: winit::event::ElementState,
+  repeat: bool,
+  modifiers: winit::keyboard::ModifiersState,
+) -> crossterm::event::KeyEvent {
+  let (code, keypad) = translate_winit_key_parts(physical_key, logical_key, location);
+
+  let kind = match element_state { // press, release or repeat
+    winit::event::ElementState::Pressed => {
+      match repeat {
+        false => crossterm::event::KeyEventKind::Press,
+        true => crossterm::event::KeyEventKind::Repeat,
+      }
+    },
+    winit::event::ElementState::Released => crossterm::event::KeyEventKind::Release,
+  };
+  
+  let mut state = crossterm::event::KeyEventState::empty(); // signifies capslock, numlock, keypad, etc.
+  state.set(crossterm::event::KeyEventState::KEYPAD, keypad);
+  
+  crossterm::event::KeyEvent{
+    code,
+    modifiers: translate_winit_modifiers(modifiers), // shift, control, alt, etc.
+    kind,
+    state,
+  }
+}
 
 pub fn translate_winit_physical(w_code: WCode) -> CCode {
   match w_code {
@@ -141,6 +542,44 @@ pub fn translate_winit_physical(w_code: WCode) -> CCode {
     WCode::Digit9 => CCode::Char('9'),
     WCode::Digit0 => CCode::Char('0'),
 
+    // --- Punctuation (US layout positions, like the letters) ---
+    WCode::Backquote => CCode::Char('`'),
+    WCode::Minus => CCode::Char('-'),
+    WCode::Equal => CCode::Char('='),
+    WCode::BracketLeft => CCode::Char('['),
+    WCode::BracketRight => CCode::Char(']'),
+    WCode::Backslash => CCode::Char('\\'),
+    WCode::IntlBackslash => CCode::Char('\\'),
+    WCode::Semicolon => CCode::Char(';'),
+    WCode::Quote => CCode::Char('\''),
+    WCode::Comma => CCode::Char(','),
+    WCode::Period => CCode::Char('.'),
+    WCode::Slash => CCode::Char('/'),
+
+    // --- Numpad (see 'translate_winit_key' for NumLock and telling them apart) ---
+    WCode::Numpad0 => CCode::Char('0'),
+    WCode::Numpad1 => CCode::Char('1'),
+    WCode::Numpad2 => CCode::Char('2'),
+    WCode::Numpad3 => CCode::Char('3'),
+    WCode::Numpad4 => CCode::Char('4'),
+    WCode::Numpad5 => CCode::Char('5'),
+    WCode::Numpad6 => CCode::Char('6'),
+    WCode::Numpad7 => CCode::Char('7'),
+    WCode::Numpad8 => CCode::Char('8'),
+    WCode::Numpad9 => CCode::Char('9'),
+    WCode::NumpadAdd => CCode::Char('+'),
+    WCode::NumpadSubtract => CCode::Char('-'),
+    WCode::NumpadMultiply | WCode::NumpadStar => CCode::Char('*'),
+    WCode::NumpadDivide => CCode::Char('/'),
+    WCode::NumpadDecimal => CCode::Char('.'),
+    WCode::NumpadComma => CCode::Char(','),
+    WCode::NumpadEqual => CCode::Char('='),
+    WCode::NumpadHash => CCode::Char('#'),
+    WCode::NumpadParenLeft => CCode::Char('('),
+    WCode::NumpadParenRight => CCode::Char(')'),
+    WCode::NumpadEnter => CCode::Enter,
+    WCode::NumpadBackspace => CCode::Backspace,
+
     // --- Functional Keys ---
     WCode::Space => CCode::Char(' '),
     WCode::Enter => CCode::Enter,
@@ -153,6 +592,12 @@ pub fn translate_winit_physical(w_code: WCode) -> CCode {
     WCode::End => CCode::End,
     WCode::PageUp => CCode::PageUp,
     WCode::PageDown => CCode::PageDown,
+    WCode::CapsLock => CCode::CapsLock,
+    WCode::ScrollLock => CCode::ScrollLock,
+    WCode::NumLock => CCode::NumLock,
+    WCode::PrintScreen => CCode::PrintScreen,
+    WCode::Pause => CCode::Pause,
+    WCode::ContextMenu => CCode::Menu,
 
     // --- Arrows ---
     WCode::ArrowUp => CCode::Up,
@@ -167,6 +612,10 @@ pub fn translate_winit_physical(w_code: WCode) -> CCode {
     WCode::ControlRight => CCode::Modifier(ModifierKeyCode::RightControl),
     WCode::AltLeft => CCode::Modifier(ModifierKeyCode::LeftAlt),
     WCode::AltRight => CCode::Modifier(ModifierKeyCode::RightAlt),
+    WCode::SuperLeft => CCode::Modifier(ModifierKeyCode::LeftSuper),
+    WCode::SuperRight => CCode::Modifier(ModifierKeyCode::RightSuper),
+    WCode::Hyper => CCode::Modifier(ModifierKeyCode::LeftHyper),
+    WCode::Meta => CCode::Modifier(ModifierKeyCode::LeftMeta),
 
     // --- Function Keys ---
     WCode::F1 => CCode::F(1),
@@ -181,6 +630,38 @@ pub fn translate_winit_physical(w_code: WCode) -> CCode {
     WCode::F10 => CCode::F(10),
     WCode::F11 => CCode::F(11),
     WCode::F12 => CCode::F(12),
+    WCode::F13 => CCode::F(13),
+    WCode::F14 => CCode::F(14),
+    WCode::F15 => CCode::F(15),
+    WCode::F16 => CCode::F(16),
+    WCode::F17 => CCode::F(17),
+    WCode::F18 => CCode::F(18),
+    WCode::F19 => CCode::F(19),
+    WCode::F20 => CCode::F(20),
+    WCode::F21 => CCode::F(21),
+    WCode::F22 => CCode::F(22),
+    WCode::F23 => CCode::F(23),
+    WCode::F24 => CCode::F(24),
+    WCode::F25 => CCode::F(25),
+    WCode::F26 => CCode::F(26),
+    WCode::F27 => CCode::F(27),
+    WCode::F28 => CCode::F(28),
+    WCode::F29 => CCode::F(29),
+    WCode::F30 => CCode::F(30),
+    WCode::F31 => CCode::F(31),
+    WCode::F32 => CCode::F(32),
+    WCode::F33 => CCode::F(33),
+    WCode::F34 => CCode::F(34),
+    WCode::F35 => CCode::F(35),
+
+    // --- Media ---
+    WCode::MediaPlayPause => CCode::Media(MediaKeyCode::PlayPause),
+    WCode::MediaStop => CCode::Media(MediaKeyCode::Stop),
+    WCode::MediaTrackNext => CCode::Media(MediaKeyCode::TrackNext),
+    WCode::MediaTrackPrevious => CCode::Media(MediaKeyCode::TrackPrevious),
+    WCode::AudioVolumeDown => CCode::Media(MediaKeyCode::LowerVolume),
+    WCode::AudioVolumeUp => CCode::Media(MediaKeyCode::RaiseVolume),
+    WCode::AudioVolumeMute => CCode::Media(MediaKeyCode::MuteVolume),
 
     // Fallback for weird keys
     _ => CCode::Null,
@@ -189,6 +670,17 @@ pub fn translate_winit_physical(w_code: WCode) -> CCode {
 
 use crossterm::event::{KeyEvent, KeyModifiers};
 
+pub fn translate_winit_modifiers(modifiers: winit::keyboard::ModifiersState) -> KeyModifiers {
+  let mut translated = KeyModifiers::empty();
+  
+  translated.set(KeyModifiers::SHIFT, modifiers.shift_key());
+  translated.set(KeyModifiers::CONTROL, modifiers.control_key());
+  translated.set(KeyModifiers::ALT, modifiers.alt_key());
+  translated.set(KeyModifiers::SUPER, modifiers.super_key());
+  
+  translated
+}
+
 pub fn normalize_crossterm_key(event: &KeyEvent) -> CCode {
   // If Shift is NOT held, just return the code as-is
   if !event.modifiers.contains(KeyModifiers::SHIFT) {
@@ -232,3 +724,157 @@ pub fn normalize_crossterm_key(event: &KeyEvent) -> CCode {
     _ => event.code,
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use crossterm::event::{KeyEventKind, KeyEventState};
+  use winit::event::ElementState;
+  use winit::keyboard::{Key, KeyLocation, ModifiersState, NamedKey, PhysicalKey, SmolStr};
+
+  fn physical(w_code: WCode, location: KeyLocation) -> (CCode, bool) {
+    translate_winit_key_parts(PhysicalKey::Code(w_code), &Key::Unidentified(winit::keyboard::NativeKey::Unidentified), location)
+  }
+
+  #[test]
+  fn synthesized_releases_let_go_of_the_modifiers() {
+    use crossterm::event::{KeyEvent, KeyModifiers};
+
+    let mut inp_man = InputManager::new();
+    inp_man.set_key_release_mode(KeyReleaseMode::Synthesized);
+
+    inp_man.process_crossterm_key(KeyEvent::new(CCode::Char('s'), KeyModifiers::CONTROL));
+    assert_eq!(inp_man.modifiers, KeyModifiers::CONTROL);
+    assert!(inp_man.input_state(&Input::Key(KeyChord::ctrl(CCode::Char('s')))).triggers(&KeyState::Pressed));
+    inp_man.cycle_events();
+
+    // the key is still auto-repeating, Ctrl stays down
+    inp_man.time += inp_man.release_timeout / 2;
+    inp_man.synthesize_releases();
+    assert_eq!(inp_man.modifiers, KeyModifiers::CONTROL);
+
+    inp_man.time += inp_man.release_timeout;
+    inp_man.synthesize_releases();
+    assert_eq!(inp_man.modifiers, KeyModifiers::empty());
+    assert_eq!(inp_man.get_key(CCode::Char('s')), KeyState::Released);
+  }
+
+  #[test]
+  fn numpad_keys_are_on_the_keypad() {
+    let table = [
+      (WCode::Numpad0, '0'), (WCode::Numpad1, '1'), (WCode::Numpad2, '2'), (WCode::Numpad3, '3'), (WCode::Numpad4, '4'),
+      (WCode::Numpad5, '5'), (WCode::Numpad6, '6'), (WCode::Numpad7, '7'), (WCode::Numpad8, '8'), (WCode::Numpad9, '9'),
+      (WCode::NumpadAdd, '+'), (WCode::NumpadSubtract, '-'), (WCode::NumpadMultiply, '*'), (WCode::NumpadDivide, '/'),
+      (WCode::NumpadDecimal, '.'), (WCode::NumpadEqual, '='),
+    ];
+
+    for (w_code, c) in table {
+      assert_eq!(physical(w_code, KeyLocation::Numpad), (CCode::Char(c), true), "{:?}", w_code);
+    }
+    assert_eq!(physical(WCode::NumpadEnter, KeyLocation::Numpad), (CCode::Enter, true));
+  }
+
+  #[test]
+  fn numpad_without_numlock_is_navigation() {
+    let arrow = translate_winit_key_parts(PhysicalKey::Code(WCode::Numpad8), &Key::Named(NamedKey::ArrowUp), KeyLocation::Numpad);
+    assert_eq!(arrow, (CCode::Up, true));
+
+    let begin = translate_winit_key_parts(PhysicalKey::Code(WCode::Numpad5), &Key::Named(NamedKey::Clear), KeyLocation::Numpad);
+    assert_eq!(begin, (CCode::KeypadBegin, true));
+  }
+
+  #[test]
+  fn punctuation_and_digits_are_not_on_the_keypad() {
+    let table = [
+      (WCode::Minus, '-'), (WCode::Equal, '='), (WCode::BracketLeft, '['), (WCode::BracketRight, ']'),
+      (WCode::Semicolon, ';'), (WCode::Slash, '/'), (WCode::Quote, '\''), (WCode::Backquote, '`'), (WCode::Digit8, '8'),
+    ];
+
+    for (w_code, c) in table {
+      assert_eq!(physical(w_code, KeyLocation::Standard), (CCode::Char(c), false), "{:?}", w_code);
+    }
+  }
+
+  #[test]
+  fn function_keys_go_up_to_f24_and_beyond() {
+    let table = [
+      WCode::F13, WCode::F14, WCode::F15, WCode::F16, WCode::F17, WCode::F18,
+      WCode::F19, WCode::F20, WCode::F21, WCode::F22, WCode::F23, WCode::F24,
+    ];
+
+    for (index, w_code) in table.into_iter().enumerate() {
+      assert_eq!(translate_winit_physical(w_code), CCode::F(13 + index as u8));
+    }
+    assert_eq!(translate_winit_physical(WCode::F35), CCode::F(35));
+  }
+
+  /// Presses a key through the winit path and, in another InputManager, through the crossterm path.
+  fn press_both(
+    w_code: WCode, logical: Key, location: KeyLocation, w_modifiers: ModifiersState,
+    c_event: crossterm::event::KeyEvent,
+  ) -> (InputManager, InputManager) {
+    let mut winit_manager = InputManager::new();
+    let translated = translate_winit_event(PhysicalKey::Code(w_code), &logical, location, ElementState::Pressed, false, w_modifiers);
+    winit_manager.process_crossterm_key(translated);
+
+    let mut crossterm_manager = InputManager::new();
+    crossterm_manager.process_crossterm_key(c_event);
+
+    (winit_manager, crossterm_manager)
+  }
+
+  fn crossterm_press(code: CCode, modifiers: KeyModifiers, state: KeyEventState) -> crossterm::event::KeyEvent {
+    crossterm::event::KeyEvent { code, modifiers, kind: KeyEventKind::Press, state }
+  }
+
+  #[test]
+  fn bindings_resolve_the_same_on_both_backends() {
+    let cases = [
+      (
+        press_both(WCode::Numpad8, Key::Character(SmolStr::new("8")), KeyLocation::Numpad, ModifiersState::empty(),
+          crossterm_press(CCode::Char('8'), KeyModifiers::empty(), KeyEventState::KEYPAD)),
+        vec![Input::Key(KeyChord::on_keypad(CCode::Char('8'))), Input::Key(CCode::Char('8').into())],
+      ),
+      (
+        press_both(WCode::NumpadAdd, Key::Character(SmolStr::new("+")), KeyLocation::Numpad, ModifiersState::empty(),
+          crossterm_press(CCode::Char('+'), KeyModifiers::empty(), KeyEventState::KEYPAD)),
+        vec![Input::Key(KeyChord::on_keypad(CCode::Char('+')))],
+      ),
+      (
+        press_both(WCode::KeyS, Key::Character(SmolStr::new("s")), KeyLocation::Standard, ModifiersState::CONTROL,
+          crossterm_press(CCode::Char('s'), KeyModifiers::CONTROL, KeyEventState::empty())),
+        vec![Input::Key(KeyChord::ctrl(CCode::Char('s')))],
+      ),
+      (
+        press_both(WCode::Slash, Key::Character(SmolStr::new("?")), KeyLocation::Standard, ModifiersState::SHIFT,
+          crossterm_press(CCode::Char('?'), KeyModifiers::SHIFT, KeyEventState::empty())),
+        vec![Input::Key(KeyChord::shift(CCode::Char('/')))],
+      ),
+      (
+        press_both(WCode::F13, Key::Named(NamedKey::F13), KeyLocation::Standard, ModifiersState::empty(),
+          crossterm_press(CCode::F(13), KeyModifiers::empty(), KeyEventState::empty())),
+        vec![Input::Key(CCode::F(13).into())],
+      ),
+    ];
+
+    for ((winit_manager, crossterm_manager), inputs) in cases {
+      for input in inputs {
+        assert_eq!(winit_manager.input_state(&input), KeyState::Pressed, "winit {:?}", input);
+        assert_eq!(crossterm_manager.input_state(&input), KeyState::Pressed, "crossterm {:?}", input);
+      }
+    }
+  }
+
+  #[test]
+  fn keypad_bindings_ignore_the_main_digits() {
+    let (winit_manager, crossterm_manager) = press_both(
+      WCode::Digit8, Key::Character(SmolStr::new("8")), KeyLocation::Standard, ModifiersState::empty(),
+      crossterm_press(CCode::Char('8'), KeyModifiers::empty(), KeyEventState::empty()),
+    );
+
+    let keypad_8 = Input::Key(KeyChord::on_keypad(CCode::Char('8')));
+    assert_eq!(winit_manager.input_state(&keypad_8), KeyState::Unactive);
+    assert_eq!(crossterm_manager.input_state(&keypad_8), KeyState::Unactive);
+  }
+}
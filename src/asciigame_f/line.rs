@@ -0,0 +1,108 @@
+/// Bresenham line between two cells. Yields every cell from 'from' to 'to', both included.
+pub struct Line {
+  current: (i32, i32),
+  to: (i32, i32),
+  delta: (i32, i32),
+  step: (i32, i32),
+  error: i32,
+  done: bool,
+}
+
+impl Line {
+  pub fn new(from: (i32, i32), to: (i32, i32)) -> Self {
+    let dx = (to.0 - from.0).abs();
+    let dy = -(to.1 - from.1).abs();
+
+    Line {
+      current: from,
+      to,
+      delta: (dx, dy),
+      step: (if from.0 < to.0 { 1 } else { -1 }, if from.1 < to.1 { 1 } else { -1 }),
+      error: dx + dy,
+      done: false,
+    }
+  }
+}
+
+impl Iterator for Line {
+  type Item = (i32, i32);
+
+  fn next(&mut self) -> Option<(i32, i32)> {
+    if self.done { return None; }
+
+    let point = self.current;
+
+    if point == self.to {
+      self.done = true;
+      return Some(point);
+    }
+
+    let doubled_error = self.error * 2;
+    if doubled_error >= self.delta.1 {
+      self.error += self.delta.1;
+      self.current.0 += self.step.0;
+    }
+    if doubled_error <= self.delta.0 {
+      self.error += self.delta.0;
+      self.current.1 += self.step.1;
+    }
+
+    Some(point)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // one target in each octant around the origin, steep and shallow in every direction
+  const OCTANTS: [(i32, i32); 8] = [(7, 3), (3, 7), (-3, 7), (-7, 3), (-7, -3), (-3, -7), (3, -7), (7, -3)];
+
+  #[test]
+  fn lines_start_and_end_on_their_endpoints() {
+    for to in OCTANTS {
+      let points: Vec<_> = Line::new((0, 0), to).collect();
+
+      assert_eq!(points.first(), Some(&(0, 0)), "to {:?}", to);
+      assert_eq!(points.last(), Some(&to), "to {:?}", to);
+    }
+  }
+
+  #[test]
+  fn lines_take_one_step_per_cell_in_every_octant() {
+    for to in OCTANTS {
+      let points: Vec<_> = Line::new((0, 0), to).collect();
+
+      assert_eq!(points.len() as i32, to.0.abs().max(to.1.abs()) + 1, "to {:?}", to);
+      for pair in points.windows(2) {
+        let step = (pair[1].0 - pair[0].0, pair[1].1 - pair[0].1);
+
+        assert!(step.0 == to.0.signum() || step.0 == 0, "to {:?}: step {:?}", to, step);
+        assert!(step.1 == to.1.signum() || step.1 == 0, "to {:?}: step {:?}", to, step);
+        assert_ne!(step, (0, 0));
+      }
+    }
+  }
+
+  #[test]
+  fn lines_stay_close_to_the_ideal_line() {
+    assert_eq!(Line::new((0, 0), (5, 2)).collect::<Vec<_>>(), [(0, 0), (1, 0), (2, 1), (3, 1), (4, 2), (5, 2)]);
+
+    for to in OCTANTS {
+      for (x, y) in Line::new((0, 0), to) {
+        // distance from the ideal line, scaled by its length
+        let off = (x * to.1 - y * to.0).abs() as f32 / ((to.0 * to.0 + to.1 * to.1) as f32).sqrt();
+
+        assert!(off <= 0.5 + f32::EPSILON, "to {:?}: ({}, {}) is {} off", to, x, y, off);
+      }
+    }
+  }
+
+  #[test]
+  fn straight_and_single_cell_lines() {
+    assert_eq!(Line::new((2, 2), (2, 2)).collect::<Vec<_>>(), [(2, 2)]);
+    assert_eq!(Line::new((3, 1), (0, 1)).collect::<Vec<_>>(), [(3, 1), (2, 1), (1, 1), (0, 1)]);
+    assert_eq!(Line::new((1, -1), (1, 1)).collect::<Vec<_>>(), [(1, -1), (1, 0), (1, 1)]);
+    assert_eq!(Line::new((0, 0), (-2, -2)).collect::<Vec<_>>(), [(0, 0), (-1, -1), (-2, -2)]);
+  }
+}
@@ -2,20 +2,48 @@
 
 pub mod character;
 pub mod draw_buffer;
+pub mod view;
+pub mod text;
+pub mod line;
+pub mod fov;
+pub mod pathfinding;
+pub mod shapes;
+pub mod camera;
 pub mod engine;
+pub mod backend;
+pub mod game;
+pub mod scene;
+pub mod replay;
 pub mod gamestate;
 pub mod input_manager;
 pub mod input_dispatcher;
+pub mod action_map;
+pub mod text_input;
 pub mod window_game;
 pub mod terminal_game;
+pub mod headless_game;
 pub mod rect_instance;
 
 pub use character::{*};
 pub use draw_buffer::{*};
+pub use view::{*};
+pub use text::{*};
+pub use line::{*};
+pub use fov::{*};
+pub use pathfinding::{*};
+pub use shapes::{*};
+pub use camera::{*};
 pub use engine::{*};
+pub use backend::{*};
+pub use game::{*};
+pub use scene::{*};
+pub use replay::{*};
 pub use gamestate::{*};
 pub use input_manager::{*};
 pub use input_dispatcher::{*};
+pub use action_map::{*};
+pub use text_input::{*};
 pub use window_game::{*};
 pub use terminal_game::{*};
+pub use headless_game::{*};
 pub use rect_instance::{*};
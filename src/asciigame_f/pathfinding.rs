@@ -0,0 +1,310 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+// Grid pathfinding. The grid itself is only known through a cost function: cost(from, to) is what stepping
+// from a cell onto its neighbour costs, or None when 'to' can't be entered (walls, cells outside of the map).
+// Costs should be at least 1, the A* heuristic counts one per step.
+
+/// Which cells a cell is connected to.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Neighbourhood {
+  /// Up, down, left and right.
+  #[default]
+  Four,
+  /// The diagonals too.
+  Eight,
+}
+
+impl Neighbourhood {
+  const FOUR: [(i32, i32); 4] = [(0, -1), (1, 0), (0, 1), (-1, 0)];
+  const EIGHT: [(i32, i32); 8] = [(0, -1), (1, 0), (0, 1), (-1, 0), (1, -1), (1, 1), (-1, 1), (-1, -1)];
+
+  pub fn offsets(&self) -> &'static [(i32, i32)] {
+    match self {
+      Neighbourhood::Four => &Self::FOUR,
+      Neighbourhood::Eight => &Self::EIGHT,
+    }
+  }
+
+  pub fn neighbours(&self, (x, y): (i32, i32)) -> impl Iterator<Item = (i32, i32)> {
+    self.offsets().iter().map(move |(dx, dy)| (x + dx, y + dy))
+  }
+
+  /// The fewest steps between two cells, ignoring obstacles.
+  pub fn distance(&self, from: (i32, i32), to: (i32, i32)) -> u32 {
+    let (dx, dy) = (from.0.abs_diff(to.0), from.1.abs_diff(to.1));
+
+    match self {
+      Neighbourhood::Four => dx + dy,
+      Neighbourhood::Eight => dx.max(dy),
+    }
+  }
+}
+
+/// The cheapest path from 'start' to 'goal', both included, and its total cost. None when the goal can't be reached.
+/// The cost function has to return None outside of the map, or an unreachable goal is searched for forever.
+pub fn astar<F>(start: (i32, i32), goal: (i32, i32), neighbourhood: Neighbourhood, mut cost: F) -> Option<(Vec<(i32, i32)>, u32)>
+where F: FnMut((i32, i32), (i32, i32)) -> Option<u32> {
+  let mut came_from = HashMap::<(i32, i32), (i32, i32)>::new();
+  let mut best_cost = HashMap::from([(start, 0)]);
+  let mut open = BinaryHeap::from([Reverse((neighbourhood.distance(start, goal), 0, start))]);
+
+  while let Some(Reverse((_, current_cost, current))) = open.pop() {
+    if current == goal {
+      let mut path = vec![current];
+      let mut cell = current;
+
+      while let Some(&previous) = came_from.get(&cell) {
+        path.push(previous);
+        cell = previous;
+      }
+      path.reverse();
+
+      return Some((path, current_cost));
+    }
+
+    // a cheaper way to this cell was found after this entry was queued
+    if best_cost.get(&current).is_some_and(|&best| best < current_cost) { continue; }
+
+    for next in neighbourhood.neighbours(current) {
+      let Some(step_cost) = cost(current, next) else { continue; };
+      let next_cost = current_cost + step_cost;
+
+      if best_cost.get(&next).is_none_or(|&best| next_cost < best) {
+        best_cost.insert(next, next_cost);
+        came_from.insert(next, current);
+        open.push(Reverse((next_cost + neighbourhood.distance(next, goal), next_cost, next)));
+      }
+    }
+  }
+
+  None
+}
+
+/// Distance from every cell of a map to the nearest goal, for monster AI, flee maps, auto-explore and the like.
+/// Anything walking downhill on it reaches a goal the cheapest way.
+///
+/// The map spreads out from the goals and asks cost(from, to) once per step, with 'from' being the cell nearer to a goal,
+/// so the values are exact when a step costs the same both ways. For costs that only depend on the cell stepped onto,
+/// a value counts the cell itself instead of the goal, so downhill stays the cheapest way as long as the goals cost the same
+/// to enter. Other costs are only approximated.
+#[derive(Clone, Debug)]
+pub struct DijkstraMap {
+  size: (usize, usize),
+  neighbourhood: Neighbourhood,
+  values: Vec<Option<i32>>, // None for cells no goal can be reached from
+}
+
+impl DijkstraMap {
+  /// A map of the distance to the nearest of the goals.
+  pub fn new<F>(size: (usize, usize), goals: impl IntoIterator<Item = (i32, i32)>, neighbourhood: Neighbourhood, cost: F) -> Self
+  where F: FnMut((i32, i32), (i32, i32)) -> Option<u32> {
+    Self::with_values(size, goals.into_iter().map(|goal| (goal, 0)), neighbourhood, cost)
+  }
+
+  /// Like 'new', but every goal starts at its own value. Lower values pull harder, e.g. -10 for a treasure next to 0s for unexplored cells.
+  pub fn with_values<F>(size: (usize, usize), goals: impl IntoIterator<Item = ((i32, i32), i32)>, neighbourhood: Neighbourhood, mut cost: F) -> Self
+  where F: FnMut((i32, i32), (i32, i32)) -> Option<u32> {
+    let mut map = Self {
+      size,
+      neighbourhood,
+      values: vec![None; size.0 * size.1],
+    };
+
+    let mut open = BinaryHeap::new();
+    for (goal, value) in goals {
+      let Some(index) = map.index(goal) else { continue; };
+
+      if map.values[index].is_none_or(|old| value < old) {
+        map.values[index] = Some(value);
+        open.push(Reverse((value, goal)));
+      }
+    }
+
+    while let Some(Reverse((value, current))) = open.pop() {
+      if map.get(current).is_some_and(|best| best < value) { continue; }
+
+      for next in neighbourhood.neighbours(current) {
+        let Some(index) = map.index(next) else { continue; };
+        // cells that can't be entered get no value, so nothing walks downhill into them
+        let Some(step_cost) = cost(current, next) else { continue; };
+        let next_value = value + step_cost as i32;
+
+        if map.values[index].is_none_or(|old| next_value < old) {
+          map.values[index] = Some(next_value);
+          open.push(Reverse((next_value, next)));
+        }
+      }
+    }
+
+    map
+  }
+
+  pub fn size(&self) -> (usize, usize) {
+    self.size
+  }
+
+  /// The cell's value. None outside of the map and for cells that can't reach any goal.
+  pub fn get(&self, cell: (i32, i32)) -> Option<i32> {
+    self.values[self.index(cell)?]
+  }
+
+  /// The neighbour with the lowest value, if it's lower than the cell's own. None once a goal (or a local minimum) is reached.
+  pub fn downhill(&self, cell: (i32, i32)) -> Option<(i32, i32)> {
+    let mut best = (cell, self.get(cell)?);
+
+    for next in self.neighbourhood.neighbours(cell) {
+      if let Some(value) = self.get(next) && value < best.1 {
+        best = (next, value);
+      }
+    }
+
+    (best.0 != cell).then_some(best.0)
+  }
+
+  /// A map to flee this one's goals on. Every value is scaled by -'factor' and rescanned, so walking downhill
+  /// runs away, but prefers open areas over dead ends. Factors around 1.2 work well.
+  pub fn flee<F>(&self, factor: f32, cost: F) -> Self
+  where F: FnMut((i32, i32), (i32, i32)) -> Option<u32> {
+    let (width, height) = self.size;
+
+    let goals = (0..height as i32)
+      .flat_map(|y| (0..width as i32).map(move |x| (x, y)))
+      .filter_map(|cell| Some((cell, (self.get(cell)? as f32 * -factor).round() as i32)));
+
+    Self::with_values(self.size, goals, self.neighbourhood, cost)
+  }
+
+  fn index(&self, (x, y): (i32, i32)) -> Option<usize> {
+    if x < 0 || y < 0 || x as usize >= self.size.0 || y as usize >= self.size.1 {
+      return None;
+    }
+
+    Some(y as usize * self.size.0 + x as usize)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // '#' is a wall, '~' is swamp that costs 5 to enter, anything else costs 1
+  const MAZE: [&str; 5] = [
+    "..........",
+    ".######...",
+    "......#...",
+    ".####.#.#.",
+    "......#.#.",
+  ];
+
+  fn cost<'a>(grid: &'a [&'a str]) -> impl Fn((i32, i32), (i32, i32)) -> Option<u32> + 'a {
+    move |_from, (x, y)| {
+      let row = grid.get(usize::try_from(y).ok()?)?.as_bytes();
+
+      match row.get(usize::try_from(x).ok()?)? {
+        b'#' => None,
+        b'~' => Some(5),
+        _ => Some(1),
+      }
+    }
+  }
+
+  fn assert_connected(path: &[(i32, i32)], neighbourhood: Neighbourhood) {
+    for step in path.windows(2) {
+      assert_eq!(neighbourhood.distance(step[0], step[1]), 1, "{:?} isn't a single step", step);
+    }
+  }
+
+  #[test]
+  fn astar_finds_the_shortest_path() {
+    let (path, total) = astar((0, 4), (7, 4), Neighbourhood::Four, cost(&MAZE)).unwrap();
+
+    assert_eq!(total, 15);
+    assert_eq!(path.len(), 16);
+    assert_eq!((path[0], path[15]), ((0, 4), (7, 4)));
+    assert_connected(&path, Neighbourhood::Four);
+
+    let (path, total) = astar((0, 4), (7, 4), Neighbourhood::Eight, cost(&MAZE)).unwrap();
+    assert_eq!(total, 13);
+    assert_connected(&path, Neighbourhood::Eight);
+  }
+
+  #[test]
+  fn astar_goes_around_expensive_cells() {
+    let grid = [
+      "...",
+      ".~.",
+      ".~.",
+      "...",
+    ];
+
+    let (path, total) = astar((1, 0), (1, 3), Neighbourhood::Four, cost(&grid)).unwrap();
+
+    assert_eq!(total, 5);
+    assert!(!path.contains(&(1, 1)) && !path.contains(&(1, 2)));
+  }
+
+  #[test]
+  fn astar_gives_up_on_unreachable_goals() {
+    let grid = ["..#.."];
+    assert_eq!(astar((0, 0), (4, 0), Neighbourhood::Four, cost(&grid)), None);
+    assert_eq!(astar((0, 0), (4, 0), Neighbourhood::Eight, cost(&grid)), None);
+    assert_eq!(astar((0, 4), (1, 1), Neighbourhood::Four, cost(&MAZE)), None, "the goal is a wall");
+  }
+
+  #[test]
+  fn dijkstra_map_measures_to_the_nearest_goal() {
+    let map = DijkstraMap::new((10, 5), [(7, 4), (0, 0)], Neighbourhood::Four, cost(&MAZE));
+
+    assert_eq!(map.get((0, 0)), Some(0));
+    assert_eq!(map.get((7, 4)), Some(0));
+    assert_eq!(map.get((0, 4)), Some(4));
+    assert_eq!(map.get((7, 0)), Some(4), "as close to (7, 4) as to (0, 0)");
+    assert_eq!(map.get((5, 4)), Some(9));
+    assert_eq!(map.get((1, 1)), None, "walls get no value");
+    assert_eq!(map.get((10, 0)), None, "outside of the map");
+  }
+
+  #[test]
+  fn dijkstra_map_goals_keep_their_values() {
+    let grid = ["......"];
+    let map = DijkstraMap::with_values((6, 1), [((0, 0), 0), ((5, 0), -4)], Neighbourhood::Four, cost(&grid));
+
+    // the -4 goal pulls harder, so it wins all of the middle
+    let values: Vec<Option<i32>> = (0..6).map(|x| map.get((x, 0))).collect();
+    assert_eq!(values, [Some(0), Some(0), Some(-1), Some(-2), Some(-3), Some(-4)]);
+  }
+
+  #[test]
+  fn walking_downhill_stops_at_a_goal() {
+    let map = DijkstraMap::new((10, 5), [(7, 4)], Neighbourhood::Four, cost(&MAZE));
+
+    let mut cell = (0, 4);
+    let mut steps = 0;
+    while let Some(next) = map.downhill(cell) {
+      assert!(map.get(next) < map.get(cell));
+      cell = next;
+      steps += 1;
+    }
+
+    assert_eq!(cell, (7, 4));
+    assert_eq!(steps, 15);
+    assert_eq!(map.downhill((7, 4)), None);
+  }
+
+  #[test]
+  fn flee_maps_lead_away_from_the_goals() {
+    let toward = DijkstraMap::new((10, 5), [(0, 0)], Neighbourhood::Four, cost(&MAZE));
+    let away = toward.flee(1.2, cost(&MAZE));
+
+    assert_eq!(away.get((1, 1)), None);
+    assert!(away.get((0, 0)) > away.get((5, 4)), "the goal is uphill from the far end");
+
+    let mut cell = (2, 0);
+    while let Some(next) = away.downhill(cell) {
+      assert!(toward.get(next) > toward.get(cell) || away.get(next) < away.get(cell));
+      cell = next;
+    }
+    assert!(toward.get(cell).unwrap() > toward.get((2, 0)).unwrap());
+  }
+}
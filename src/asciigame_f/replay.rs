@@ -0,0 +1,487 @@
+use crate::{InputManager, KeyState, TextEvent};
+
+use crossterm::event::{KeyCode, KeyModifiers, MediaKeyCode, ModifierKeyCode, MouseButton};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use anyhow::{Result, anyhow, bail};
+
+// Replay files are plain text: a "seed <n>" line followed by one "frame" line per engine frame,
+// listing every key and mouse button that wasn't Unactive, plus the held modifiers, the cursor, the wheel and the typed text,
+// e.g. "frame Char:119=Pressed Esc=Released Keypad:Char:56=Held Mouse:Left=Held Mods=SHIFT|CONTROL Cursor=12,3 Wheel=0,-1 Text=72,105,Backspace".
+// Typed characters are written as their code points.
+
+/// The input state of a single frame.
+pub struct RecordedFrame {
+  pub keys: Vec<(KeyCode, KeyState)>,
+  pub keypad_keys: Vec<(KeyCode, KeyState)>,
+  pub modifiers: KeyModifiers,
+  pub mouse_buttons: Vec<(MouseButton, KeyState)>,
+  pub mouse_position: Option<(i32, i32)>,
+  pub wheel: (i32, i32),
+  pub text_events: Vec<TextEvent>,
+}
+
+impl Default for RecordedFrame {
+  fn default() -> Self {
+    Self {
+      keys: Vec::new(),
+      keypad_keys: Vec::new(),
+      modifiers: KeyModifiers::empty(),
+      mouse_buttons: Vec::new(),
+      mouse_position: None,
+      wheel: (0, 0),
+      text_events: Vec::new(),
+    }
+  }
+}
+
+/// Input of a whole session, loaded from a replay file.
+pub struct Recording {
+  pub seed: u64,
+  pub frames: Vec<RecordedFrame>,
+}
+
+impl Recording {
+  pub fn load(path: &Path) -> Result<Self> {
+    let reader = BufReader::new(File::open(path)?);
+
+    let mut seed = None;
+    let mut frames = Vec::new();
+
+    for (line_index, line) in reader.lines().enumerate() {
+      let line = line?;
+      let mut words = line.split_whitespace();
+
+      match words.next() {
+        Some("seed") => {
+          let value = words.next().ok_or_else(|| anyhow!("line {}: missing seed", line_index + 1))?;
+          seed = Some(value.parse()?);
+        },
+        Some("frame") => {
+          let mut frame = RecordedFrame::default();
+
+          for word in words {
+            if decode_entry(word, &mut frame).is_none() {
+              bail!("line {}: bad entry '{}'", line_index + 1, word);
+            }
+          }
+
+          frames.push(frame);
+        },
+        None => (),
+        Some(other) => bail!("line {}: unknown entry '{}'", line_index + 1, other),
+      }
+    }
+
+    let Some(seed) = seed else { bail!("{}: no seed in replay file", path.display()) };
+
+    Ok(Self { seed, frames })
+  }
+}
+
+/// Writes the input of every frame to a replay file as the game runs.
+/// Each frame is flushed right away, so the file is still usable after a crash.
+pub struct Recorder {
+  writer: BufWriter<File>,
+}
+
+impl Recorder {
+  pub fn create(path: &Path, seed: u64) -> Result<Self> {
+    let mut writer = BufWriter::new(File::create(path)?);
+
+    writeln!(writer, "seed {}", seed)?;
+    writer.flush()?;
+
+    Ok(Self { writer })
+  }
+
+  pub fn record_frame(&mut self, inp_man: &InputManager) -> Result<()> {
+    let mut entries: Vec<String> = inp_man.key_events.iter()
+      .filter(|(_, state)| **state != KeyState::Unactive)
+      .map(|(key, state)| format!("{}={}", encode_key(*key), encode_state(state)))
+      .collect();
+    entries.extend(inp_man.keypad_keys.iter()
+      .filter(|(_, state)| **state != KeyState::Unactive)
+      .map(|(key, state)| format!("Keypad:{}={}", encode_key(*key), encode_state(state))));
+    entries.extend(inp_man.mouse_buttons.iter()
+      .filter(|(_, state)| **state != KeyState::Unactive)
+      .map(|(button, state)| format!("Mouse:{}={}", encode_button(*button), encode_state(state))));
+    entries.sort(); // HashMap order is random, keep the files diffable
+
+    if !inp_man.modifiers.is_empty() {
+      let names: Vec<&str> = inp_man.modifiers.iter_names().map(|(name, _)| name).collect();
+      entries.push(format!("Mods={}", names.join("|")));
+    }
+    if let Some((col, row)) = inp_man.mouse_position {
+      entries.push(format!("Cursor={},{}", col, row));
+    }
+    if inp_man.wheel != (0, 0) {
+      entries.push(format!("Wheel={},{}", inp_man.wheel.0, inp_man.wheel.1));
+    }
+    if !inp_man.text_events.is_empty() {
+      let tokens: Vec<String> = inp_man.text_events.iter().flat_map(encode_text_event).collect();
+      entries.push(format!("Text={}", tokens.join(",")));
+    }
+
+    write!(self.writer, "frame")?;
+    for entry in entries {
+      write!(self.writer, " {}", entry)?;
+    }
+    writeln!(self.writer)?;
+    self.writer.flush()?;
+
+    Ok(())
+  }
+}
+
+/// Feeds a Recording back into the InputManager, one frame at a time.
+pub struct Player {
+  recording: Recording,
+  next_frame: usize,
+}
+
+impl Player {
+  pub fn new(recording: Recording) -> Self {
+    Self {
+      recording,
+      next_frame: 0,
+    }
+  }
+
+  /// Replaces the InputManager's state with the next recorded frame. Returns false once the recording ran out.
+  pub fn play_frame(&mut self, inp_man: &mut InputManager) -> bool {
+    let Some(frame) = self.recording.frames.get(self.next_frame) else {
+      return false;
+    };
+
+    inp_man.key_events = frame.keys.iter().cloned().collect();
+    inp_man.keypad_keys = frame.keypad_keys.iter().cloned().collect();
+    inp_man.modifiers = frame.modifiers;
+    inp_man.mouse_buttons = frame.mouse_buttons.iter().cloned().collect();
+    inp_man.mouse_position = frame.mouse_position;
+    inp_man.wheel = frame.wheel;
+    inp_man.text_events = frame.text_events.clone();
+    self.next_frame += 1;
+
+    true
+  }
+}
+
+/// Reads one "name=value" word of a frame line into 'frame'.
+fn decode_entry(word: &str, frame: &mut RecordedFrame) -> Option<()> {
+  let (name, value) = word.split_once('=')?;
+  let pair = || -> Option<(i32, i32)> {
+    let (a, b) = value.split_once(',')?;
+    Some((a.parse().ok()?, b.parse().ok()?))
+  };
+
+  match name {
+    "Cursor" => frame.mouse_position = Some(pair()?),
+    "Wheel" => frame.wheel = pair()?,
+    "Text" => {
+      for token in value.split(',') {
+        frame.text_events.push(decode_text_event(token)?);
+      }
+    },
+    "Mods" => {
+      for modifier in value.split('|') {
+        frame.modifiers |= KeyModifiers::from_name(modifier)?;
+      }
+    },
+    _ => if let Some(button) = name.strip_prefix("Mouse:") {
+      frame.mouse_buttons.push((decode_button(button)?, decode_state(value)?));
+    } else if let Some(key) = name.strip_prefix("Keypad:") {
+      frame.keypad_keys.push((decode_key(key)?, decode_state(value)?));
+    } else {
+      frame.keys.push((decode_key(name)?, decode_state(value)?));
+    },
+  }
+
+  Some(())
+}
+
+/// One token per typed character, so the words stay free of whitespace.
+fn encode_text_event(event: &TextEvent) -> Vec<String> {
+  let name = match event {
+    TextEvent::Insert(text) => return text.chars().map(|c| (c as u32).to_string()).collect(),
+    TextEvent::Backspace => "Backspace",
+    TextEvent::Delete => "Delete",
+    TextEvent::Left => "Left",
+    TextEvent::Right => "Right",
+    TextEvent::Home => "Home",
+    TextEvent::End => "End",
+    TextEvent::Submit => "Submit",
+    TextEvent::Cancel => "Cancel",
+  };
+
+  vec![name.to_string()]
+}
+
+fn decode_text_event(token: &str) -> Option<TextEvent> {
+  Some(match token {
+    "Backspace" => TextEvent::Backspace,
+    "Delete" => TextEvent::Delete,
+    "Left" => TextEvent::Left,
+    "Right" => TextEvent::Right,
+    "Home" => TextEvent::Home,
+    "End" => TextEvent::End,
+    "Submit" => TextEvent::Submit,
+    "Cancel" => TextEvent::Cancel,
+    _ => TextEvent::Insert(char::from_u32(token.parse().ok()?)?.to_string()),
+  })
+}
+
+pub(crate) fn encode_button(button: MouseButton) -> &'static str {
+  match button {
+    MouseButton::Left => "Left",
+    MouseButton::Right => "Right",
+    MouseButton::Middle => "Middle",
+  }
+}
+
+pub(crate) fn decode_button(text: &str) -> Option<MouseButton> {
+  Some(match text {
+    "Left" => MouseButton::Left,
+    "Right" => MouseButton::Right,
+    "Middle" => MouseButton::Middle,
+    _ => return None,
+  })
+}
+
+fn encode_state(state: &KeyState) -> &'static str {
+  match state {
+    KeyState::Pressed => "Pressed",
+    KeyState::Held => "Held",
+    KeyState::Released => "Released",
+    KeyState::Unactive => "Unactive",
+    KeyState::PressedAndReleased => "PressedAndReleased",
+    KeyState::Down => "Down",
+  }
+}
+
+fn decode_state(text: &str) -> Option<KeyState> {
+  Some(match text {
+    "Pressed" => KeyState::Pressed,
+    "Held" => KeyState::Held,
+    "Released" => KeyState::Released,
+    "Unactive" => KeyState::Unactive,
+    "PressedAndReleased" => KeyState::PressedAndReleased,
+    "Down" => KeyState::Down,
+    _ => return None,
+  })
+}
+
+const NAMED_KEYS: [(KeyCode, &str); 23] = [
+  (KeyCode::Backspace, "Backspace"),
+  (KeyCode::Enter, "Enter"),
+  (KeyCode::Left, "Left"),
+  (KeyCode::Right, "Right"),
+  (KeyCode::Up, "Up"),
+  (KeyCode::Down, "Down"),
+  (KeyCode::Home, "Home"),
+  (KeyCode::End, "End"),
+  (KeyCode::PageUp, "PageUp"),
+  (KeyCode::PageDown, "PageDown"),
+  (KeyCode::Tab, "Tab"),
+  (KeyCode::BackTab, "BackTab"),
+  (KeyCode::Delete, "Delete"),
+  (KeyCode::Insert, "Insert"),
+  (KeyCode::Null, "Null"),
+  (KeyCode::Esc, "Esc"),
+  (KeyCode::CapsLock, "CapsLock"),
+  (KeyCode::ScrollLock, "ScrollLock"),
+  (KeyCode::NumLock, "NumLock"),
+  (KeyCode::PrintScreen, "PrintScreen"),
+  (KeyCode::Pause, "Pause"),
+  (KeyCode::Menu, "Menu"),
+  (KeyCode::KeypadBegin, "KeypadBegin"),
+];
+
+const MEDIA_KEYS: [(MediaKeyCode, &str); 13] = [
+  (MediaKeyCode::Play, "Play"),
+  (MediaKeyCode::Pause, "Pause"),
+  (MediaKeyCode::PlayPause, "PlayPause"),
+  (MediaKeyCode::Reverse, "Reverse"),
+  (MediaKeyCode::Stop, "Stop"),
+  (MediaKeyCode::FastForward, "FastForward"),
+  (MediaKeyCode::Rewind, "Rewind"),
+  (MediaKeyCode::TrackNext, "TrackNext"),
+  (MediaKeyCode::TrackPrevious, "TrackPrevious"),
+  (MediaKeyCode::Record, "Record"),
+  (MediaKeyCode::LowerVolume, "LowerVolume"),
+  (MediaKeyCode::RaiseVolume, "RaiseVolume"),
+  (MediaKeyCode::MuteVolume, "MuteVolume"),
+];
+
+const MODIFIER_KEYS: [(ModifierKeyCode, &str); 14] = [
+  (ModifierKeyCode::LeftShift, "LeftShift"),
+  (ModifierKeyCode::LeftControl, "LeftControl"),
+  (ModifierKeyCode::LeftAlt, "LeftAlt"),
+  (ModifierKeyCode::LeftSuper, "LeftSuper"),
+  (ModifierKeyCode::LeftHyper, "LeftHyper"),
+  (ModifierKeyCode::LeftMeta, "LeftMeta"),
+  (ModifierKeyCode::RightShift, "RightShift"),
+  (ModifierKeyCode::RightControl, "RightControl"),
+  (ModifierKeyCode::RightAlt, "RightAlt"),
+  (ModifierKeyCode::RightSuper, "RightSuper"),
+  (ModifierKeyCode::RightHyper, "RightHyper"),
+  (ModifierKeyCode::RightMeta, "RightMeta"),
+  (ModifierKeyCode::IsoLevel3Shift, "IsoLevel3Shift"),
+  (ModifierKeyCode::IsoLevel5Shift, "IsoLevel5Shift"),
+];
+
+/// Turns a key into a single whitespace-free word.
+pub(crate) fn encode_key(key: KeyCode) -> String {
+  match key {
+    KeyCode::Char(c) => format!("Char:{}", c as u32),
+    KeyCode::F(n) => format!("F:{}", n),
+    KeyCode::Media(media) => format!("Media:{}", name_in(&MEDIA_KEYS, media)),
+    KeyCode::Modifier(modifier) => format!("Modifier:{}", name_in(&MODIFIER_KEYS, modifier)),
+    _ => name_in(&NAMED_KEYS, key).to_string(),
+  }
+}
+
+// the tables list every key crossterm has
+fn name_in<T>(table: &[(T, &'static str)], key: T) -> &'static str
+where T: PartialEq {
+  table.iter().find(|(code, _)| *code == key).map(|(_, name)| *name).expect("a key missing from the name tables")
+}
+
+pub(crate) fn decode_key(text: &str) -> Option<KeyCode> {
+  if let Some((code, _)) = NAMED_KEYS.iter().find(|(_, name)| *name == text) {
+    return Some(*code);
+  }
+
+  let (kind, value) = text.split_once(':')?;
+
+  match kind {
+    "Char" => Some(KeyCode::Char(char::from_u32(value.parse().ok()?)?)),
+    "F" => Some(KeyCode::F(value.parse().ok()?)),
+    "Media" => {
+      MEDIA_KEYS.iter()
+        .find(|(_, name)| *name == value)
+        .map(|(code, _)| KeyCode::Media(*code))
+    },
+    "Modifier" => {
+      MODIFIER_KEYS.iter()
+        .find(|(_, name)| *name == value)
+        .map(|(code, _)| KeyCode::Modifier(*code))
+    },
+    _ => None,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{Engine, EngineConfig, Game, GameState, HeadlessBackend, HeadlessGame};
+  use rand::Rng;
+  use std::path::PathBuf;
+
+  // moves with 'd', rolls a die every frame and jumps when Play/Pause is pressed
+  #[derive(PartialEq, Debug)]
+  struct Dice {
+    x: i32,
+    rolls: Vec<u32>,
+    jumps: u32,
+  }
+
+  impl GameState for Dice {
+    fn new(ctx: &mut Engine<Self>) -> Self {
+      ctx.bind(KeyCode::Char('d'), KeyState::Pressed, |gs: &mut Dice, _ctx| { gs.x += 1; });
+      ctx.bind(KeyCode::Media(MediaKeyCode::PlayPause), KeyState::Pressed, |gs: &mut Dice, _ctx| { gs.jumps += 1; });
+
+      Self { x: 0, rolls: vec![], jumps: 0 }
+    }
+
+    fn update(&mut self, ctx: &mut Engine<Self>) {
+      let roll = ctx.rng().random_range(1..=6);
+      self.rolls.push(roll);
+    }
+
+    fn draw(&mut self, _ctx: &mut Engine<Self>) {}
+
+    fn should_run(&mut self) -> bool {
+      true
+    }
+  }
+
+  fn temp_file(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("asciigame_{}_{}", std::process::id(), name))
+  }
+
+  #[test]
+  fn a_replay_plays_the_game_again() {
+    let path = temp_file("replay.txt");
+
+    let config = EngineConfig { seed: Some(1234), record: Some(path.clone()), ..Default::default() };
+    let mut recorded: HeadlessGame<Dice> = Game::with_config(HeadlessBackend::new((10, 10)), config).unwrap();
+
+    let media = KeyCode::Media(MediaKeyCode::PlayPause);
+    recorded.press_key(KeyCode::Char('d')).step().unwrap();
+    recorded.release_key(KeyCode::Char('d')).step().unwrap();
+    recorded.press_key(media).step().unwrap();
+    recorded.release_key(media).press_key(KeyCode::Char('d')).step().unwrap();
+    recorded.step_n(3).unwrap();
+    assert_eq!((recorded.game_state().x, recorded.game_state().jumps), (2, 1));
+
+    let recording = Recording::load(&path).unwrap();
+    assert_eq!(recording.seed, 1234);
+    assert_eq!(recording.frames.len(), 7);
+
+    // no seed given, it comes from the file
+    let config = EngineConfig { replay: Some(path.clone()), ..Default::default() };
+    let mut replayed: HeadlessGame<Dice> = Game::with_config(HeadlessBackend::new((10, 10)), config).unwrap();
+    assert!(replayed.engine().is_replaying());
+    assert_eq!(replayed.step_n(7).unwrap(), 7);
+
+    assert_eq!(replayed.engine().seed(), 1234);
+    assert_eq!(replayed.game_state(), recorded.game_state());
+
+    // the recording ran out, the player has the controls again
+    replayed.step().unwrap();
+    assert!(!replayed.engine().is_replaying());
+
+    std::fs::remove_file(path).unwrap();
+  }
+
+  #[test]
+  fn every_key_has_a_name() {
+    let keys = [
+      KeyCode::Char('w'), KeyCode::Char(' '), KeyCode::F(12), KeyCode::Esc, KeyCode::KeypadBegin,
+      KeyCode::Media(MediaKeyCode::MuteVolume), KeyCode::Modifier(ModifierKeyCode::RightAlt),
+    ];
+
+    for key in keys {
+      assert_eq!(decode_key(&encode_key(key)), Some(key), "{}", encode_key(key));
+    }
+  }
+
+  #[test]
+  fn malformed_replays_are_rejected() {
+    let path = temp_file("malformed.txt");
+
+    let lines = [
+      "seed 1\nframe Char:119=Pressed Mouse:Left=Held Mods=SHIFT Cursor=1,2 Wheel=0,-1 Text=72,Backspace\n",
+      "seed 1\nframe Char:119=Sometimes\n",
+      "seed 1\nframe Char:119\n",
+      "seed 1\nframe Jump=Pressed\n",
+      "seed 1\nframe Mouse:Back=Pressed\n",
+      "seed 1\nframe Cursor=1\n",
+      "seed 1\nframe Mods=SHIFTY\n",
+      "seed 1\nframe Text=-1\n",
+      "seed one\nframe\n",
+      "seed 1\nframes\n",
+      "frame\n",
+    ];
+
+    for (index, text) in lines.into_iter().enumerate() {
+      std::fs::write(&path, text).unwrap();
+      assert_eq!(Recording::load(&path).is_ok(), index == 0, "{:?}", text);
+    }
+
+    std::fs::remove_file(path).unwrap();
+  }
+}
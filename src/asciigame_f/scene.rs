@@ -0,0 +1,318 @@
+use crate::{Engine, GameState, InputDispatcher};
+
+/// What the scene stack should do once a scene is done updating.
+pub enum SceneCommand<GS> {
+  None,
+  Push(SceneBox<GS>),
+  Pop,
+  Replace(SceneBox<GS>),
+}
+
+impl<GS> SceneCommand<GS>
+where GS: GameState {
+  pub fn push<S>(scene: S) -> Self
+  where S: Scene<GS> {
+    SceneCommand::Push(SceneBox::new(scene))
+  }
+
+  pub fn replace<S>(scene: S) -> Self
+  where S: Scene<GS> {
+    SceneCommand::Replace(SceneBox::new(scene))
+  }
+}
+
+/// A self-contained screen (main menu, dungeon, inventory, ...) that lives on the Engine's scene stack.
+/// 'GS' is the GameState, which is shared by every scene. Only the top scene gets input and updates.
+pub trait Scene<GS>: Sized + 'static {
+  /// Called when the scene gets on the stack. The scene's own key bindings should be registered here,
+  /// they only fire while it's the top scene and are dropped together with it. Their callbacks get the scene and the Engine.
+  fn on_enter(&mut self, _bindings: &mut InputDispatcher<Self, Engine<GS>>, _gs: &mut GS, _ctx: &mut Engine<GS>) {}
+
+  /// Called right before the scene is popped or replaced.
+  fn on_exit(&mut self, _gs: &mut GS, _ctx: &mut Engine<GS>) {}
+
+  /// Called when another scene is pushed on top of this one.
+  fn on_pause(&mut self, _gs: &mut GS, _ctx: &mut Engine<GS>) {}
+
+  /// Called when this scene becomes the top one again.
+  fn on_resume(&mut self, _gs: &mut GS, _ctx: &mut Engine<GS>) {}
+
+  fn update(&mut self, gs: &mut GS, ctx: &mut Engine<GS>) -> SceneCommand<GS>;
+
+  fn draw(&mut self, gs: &mut GS, ctx: &mut Engine<GS>);
+
+  /// Overlays return true so the scenes below them keep drawing underneath.
+  fn draws_below(&self) -> bool {
+    false
+  }
+}
+
+// Type-erased scene, so scenes of different types can share one stack.
+trait AnyScene<GS> {
+  fn enter(&mut self, gs: &mut GS, ctx: &mut Engine<GS>);
+  fn exit(&mut self, gs: &mut GS, ctx: &mut Engine<GS>);
+  fn pause(&mut self, gs: &mut GS, ctx: &mut Engine<GS>);
+  fn resume(&mut self, gs: &mut GS, ctx: &mut Engine<GS>);
+  fn dispatch(&mut self, ctx: &mut Engine<GS>);
+  fn update(&mut self, gs: &mut GS, ctx: &mut Engine<GS>) -> SceneCommand<GS>;
+  fn draw(&mut self, gs: &mut GS, ctx: &mut Engine<GS>);
+  fn draws_below(&self) -> bool;
+}
+
+// A scene together with the bindings it owns.
+struct SceneSlot<S, GS> {
+  scene: S,
+  bindings: InputDispatcher<S, Engine<GS>>,
+}
+
+impl<GS, S> AnyScene<GS> for SceneSlot<S, GS>
+where S: Scene<GS> {
+  fn enter(&mut self, gs: &mut GS, ctx: &mut Engine<GS>) {
+    self.scene.on_enter(&mut self.bindings, gs, ctx);
+  }
+
+  fn exit(&mut self, gs: &mut GS, ctx: &mut Engine<GS>) {
+    self.scene.on_exit(gs, ctx);
+  }
+
+  fn pause(&mut self, gs: &mut GS, ctx: &mut Engine<GS>) {
+    self.scene.on_pause(gs, ctx);
+  }
+
+  fn resume(&mut self, gs: &mut GS, ctx: &mut Engine<GS>) {
+    self.scene.on_resume(gs, ctx);
+  }
+
+  fn dispatch(&mut self, ctx: &mut Engine<GS>) {
+    self.bindings.dispatch(&mut self.scene, ctx);
+  }
+
+  fn update(&mut self, gs: &mut GS, ctx: &mut Engine<GS>) -> SceneCommand<GS> {
+    self.scene.update(gs, ctx)
+  }
+
+  fn draw(&mut self, gs: &mut GS, ctx: &mut Engine<GS>) {
+    self.scene.draw(gs, ctx);
+  }
+
+  fn draws_below(&self) -> bool {
+    self.scene.draws_below()
+  }
+}
+
+/// A boxed scene of any type, ready to be pushed on the stack.
+pub struct SceneBox<GS>(Box<dyn AnyScene<GS>>);
+
+impl<GS> SceneBox<GS>
+where GS: GameState {
+  pub fn new<S>(scene: S) -> Self
+  where S: Scene<GS> {
+    SceneBox(Box::new(SceneSlot {
+      scene,
+      bindings: InputDispatcher::new(),
+    }))
+  }
+}
+
+/// Stack of scenes. The top one receives input and updates, the ones below are paused.
+pub struct SceneStack<GS> {
+  scenes: Vec<SceneBox<GS>>,
+}
+
+impl<GS> Default for SceneStack<GS> {
+  fn default() -> Self {
+    Self {
+      scenes: Vec::new(),
+    }
+  }
+}
+
+impl<GS> SceneStack<GS>
+where GS: GameState {
+  pub fn len(&self) -> usize {
+    self.scenes.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.scenes.is_empty()
+  }
+
+  fn apply(&mut self, command: SceneCommand<GS>, gs: &mut GS, ctx: &mut Engine<GS>) {
+    match command {
+      SceneCommand::None => (),
+      SceneCommand::Push(mut scene) => {
+        if let Some(top) = self.scenes.last_mut() {
+          top.0.pause(gs, ctx);
+        }
+        scene.0.enter(gs, ctx);
+        self.scenes.push(scene);
+      },
+      SceneCommand::Pop => {
+        if let Some(mut top) = self.scenes.pop() {
+          top.0.exit(gs, ctx);
+        }
+        if let Some(top) = self.scenes.last_mut() {
+          top.0.resume(gs, ctx);
+        }
+      },
+      SceneCommand::Replace(mut scene) => {
+        if let Some(mut top) = self.scenes.pop() {
+          top.0.exit(gs, ctx);
+        }
+        scene.0.enter(gs, ctx);
+        self.scenes.push(scene);
+      },
+    }
+  }
+
+  /// Applies every command queued on the Engine, in order.
+  pub fn apply_queued(&mut self, gs: &mut GS, ctx: &mut Engine<GS>) {
+    // the scenes may queue more commands while these are applied, they go next
+    while !ctx.scene_commands.is_empty() {
+      for command in std::mem::take(&mut ctx.scene_commands) {
+        self.apply(command, gs, ctx);
+      }
+    }
+  }
+
+  pub fn dispatch(&mut self, ctx: &mut Engine<GS>) {
+    if let Some(top) = self.scenes.last_mut() {
+      top.0.dispatch(ctx);
+    }
+  }
+
+  pub fn update(&mut self, gs: &mut GS, ctx: &mut Engine<GS>) {
+    if let Some(top) = self.scenes.last_mut() {
+      let command = top.0.update(gs, ctx);
+      self.apply(command, gs, ctx);
+    }
+
+    self.apply_queued(gs, ctx);
+  }
+
+  /// Draws the top scene, preceded by every scene below it that stays visible.
+  pub fn draw(&mut self, gs: &mut GS, ctx: &mut Engine<GS>) {
+    let mut first_visible = self.scenes.len();
+    while first_visible > 0 {
+      first_visible -= 1;
+      if !self.scenes[first_visible].0.draws_below() { break; }
+    }
+
+    for scene in self.scenes.iter_mut().skip(first_visible) {
+      scene.0.draw(gs, ctx);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::HeadlessGame;
+
+  struct Log {
+    events: Vec<String>,
+  }
+
+  impl GameState for Log {
+    fn new(ctx: &mut Engine<Self>) -> Self {
+      ctx.push_scene(Logged::new("a"));
+
+      Self { events: vec![] }
+    }
+
+    fn update(&mut self, _ctx: &mut Engine<Self>) {}
+
+    fn draw(&mut self, _ctx: &mut Engine<Self>) {}
+
+    fn should_run(&mut self) -> bool {
+      true
+    }
+  }
+
+  // writes every hook it gets to the Log
+  struct Logged {
+    name: &'static str,
+    push_on_enter: Option<&'static str>,
+  }
+
+  impl Logged {
+    fn new(name: &'static str) -> Self {
+      Self { name, push_on_enter: None }
+    }
+  }
+
+  impl Scene<Log> for Logged {
+    fn on_enter(&mut self, _bindings: &mut InputDispatcher<Self, Engine<Log>>, gs: &mut Log, ctx: &mut Engine<Log>) {
+      gs.events.push(format!("{} enter", self.name));
+
+      if let Some(name) = self.push_on_enter {
+        ctx.push_scene(Logged::new(name));
+      }
+    }
+
+    fn on_exit(&mut self, gs: &mut Log, _ctx: &mut Engine<Log>) {
+      gs.events.push(format!("{} exit", self.name));
+    }
+
+    fn on_pause(&mut self, gs: &mut Log, _ctx: &mut Engine<Log>) {
+      gs.events.push(format!("{} pause", self.name));
+    }
+
+    fn on_resume(&mut self, gs: &mut Log, _ctx: &mut Engine<Log>) {
+      gs.events.push(format!("{} resume", self.name));
+    }
+
+    fn update(&mut self, gs: &mut Log, _ctx: &mut Engine<Log>) -> SceneCommand<Log> {
+      gs.events.push(format!("{} update", self.name));
+
+      SceneCommand::None
+    }
+
+    fn draw(&mut self, _gs: &mut Log, _ctx: &mut Engine<Log>) {}
+  }
+
+  fn step_and_take_events(game: &mut HeadlessGame<Log>) -> Vec<String> {
+    game.step().unwrap();
+
+    std::mem::take(&mut game.game_state().events)
+  }
+
+  #[test]
+  fn push_pop_and_replace_call_the_hooks_in_order() {
+    let mut game = HeadlessGame::<Log>::new((10, 10)).unwrap();
+    assert_eq!(step_and_take_events(&mut game), ["a enter", "a update"]);
+
+    game.engine().push_scene(Logged::new("b"));
+    assert_eq!(step_and_take_events(&mut game), ["a pause", "b enter", "b update"]);
+
+    game.engine().pop_scene();
+    assert_eq!(step_and_take_events(&mut game), ["b exit", "a resume", "a update"]);
+
+    game.engine().replace_scene(Logged::new("c"));
+    assert_eq!(step_and_take_events(&mut game), ["a exit", "c enter", "c update"]);
+
+    game.engine().pop_scene();
+    assert_eq!(step_and_take_events(&mut game), ["c exit"]);
+  }
+
+  #[test]
+  fn queued_commands_run_in_the_order_they_were_queued() {
+    let mut game = HeadlessGame::<Log>::new((10, 10)).unwrap();
+    step_and_take_events(&mut game);
+
+    game.engine().push_scene(Logged::new("b"));
+    game.engine().push_scene(Logged::new("c"));
+    game.engine().pop_scene();
+    assert_eq!(step_and_take_events(&mut game), ["a pause", "b enter", "b pause", "c enter", "c exit", "b resume", "b update"]);
+  }
+
+  #[test]
+  fn commands_queued_by_a_hook_run_after_the_current_ones() {
+    let mut game = HeadlessGame::<Log>::new((10, 10)).unwrap();
+    step_and_take_events(&mut game);
+
+    game.engine().push_scene(Logged { name: "b", push_on_enter: Some("c") });
+    game.engine().replace_scene(Logged::new("d"));
+    assert_eq!(step_and_take_events(&mut game), ["a pause", "b enter", "b exit", "d enter", "d pause", "c enter", "c update"]);
+  }
+}
@@ -0,0 +1,197 @@
+use crate::{Character, DrawTarget, Line, Rect};
+
+/// Characters used for the borders drawn by 'DrawShapes::draw_box'.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum BorderStyle {
+  #[default]
+  Single,
+  Double,
+  Heavy,
+  Ascii, // for fonts without box-drawing characters
+}
+
+impl BorderStyle {
+  /// [top-left, top-right, bottom-left, bottom-right, horizontal, vertical]
+  pub fn chars(&self) -> [char; 6] {
+    match self {
+      BorderStyle::Single => ['┌', '┐', '└', '┘', '─', '│'],
+      BorderStyle::Double => ['╔', '╗', '╚', '╝', '═', '║'],
+      BorderStyle::Heavy => ['┏', '┓', '┗', '┛', '━', '┃'],
+      BorderStyle::Ascii => ['+', '+', '+', '+', '-', '|'],
+    }
+  }
+}
+
+/// Shape drawing on anything that implements DrawTarget. Everything is clipped to the target.
+pub trait DrawShapes: DrawTarget {
+  fn line(&mut self, from: (i32, i32), to: (i32, i32), character: Character) {
+    for (col, row) in Line::new(from, to) {
+      self.put_char(col, row, character);
+    }
+  }
+
+  fn fill_rect(&mut self, rect: Rect, character: Character) {
+    for row in rect.y..rect.y + rect.height as i32 {
+      for col in rect.x..rect.x + rect.width as i32 {
+        self.put_char(col, row, character);
+      }
+    }
+  }
+
+  /// Outlines the rectangle with 'character'.
+  fn outline_rect(&mut self, rect: Rect, character: Character) {
+    if rect.width == 0 || rect.height == 0 { return; }
+
+    let (right, bottom) = (rect.x + rect.width as i32 - 1, rect.y + rect.height as i32 - 1);
+
+    self.line((rect.x, rect.y), (right, rect.y), character);
+    self.line((rect.x, bottom), (right, bottom), character);
+    self.line((rect.x, rect.y), (rect.x, bottom), character);
+    self.line((right, rect.y), (right, bottom), character);
+  }
+
+  /// Outlines the rectangle with box-drawing characters. Their colors come from 'template'.
+  fn draw_box(&mut self, rect: Rect, border: BorderStyle, template: Character) {
+    if rect.width < 2 || rect.height < 2 {
+      return self.outline_rect(rect, template);
+    }
+
+    let [top_left, top_right, bottom_left, bottom_right, horizontal, vertical] = border.chars();
+    let with = |symbol| Character { symbol, ..template };
+
+    let (right, bottom) = (rect.x + rect.width as i32 - 1, rect.y + rect.height as i32 - 1);
+
+    self.line((rect.x + 1, rect.y), (right - 1, rect.y), with(horizontal));
+    self.line((rect.x + 1, bottom), (right - 1, bottom), with(horizontal));
+    self.line((rect.x, rect.y + 1), (rect.x, bottom - 1), with(vertical));
+    self.line((right, rect.y + 1), (right, bottom - 1), with(vertical));
+
+    self.put_char(rect.x, rect.y, with(top_left));
+    self.put_char(right, rect.y, with(top_right));
+    self.put_char(rect.x, bottom, with(bottom_left));
+    self.put_char(right, bottom, with(bottom_right));
+  }
+
+  /// Ellipse centered on 'center' with the radii 'radius_x' and 'radius_y', filled or only outlined.
+  fn ellipse(&mut self, center: (i32, i32), radius_x: i32, radius_y: i32, character: Character, filled: bool) {
+    // the half cell of slack keeps small ellipses from looking like diamonds
+    let (rx, ry) = (radius_x as f32 + 0.5, radius_y as f32 + 0.5);
+    let inside = |dx: i32, dy: i32| (dx as f32 / rx).powi(2) + (dy as f32 / ry).powi(2) <= 1.0;
+
+    for dy in -radius_y..=radius_y {
+      for dx in -radius_x..=radius_x {
+        if !inside(dx, dy) { continue; }
+
+        let on_edge = !inside(dx + 1, dy) || !inside(dx - 1, dy) || !inside(dx, dy + 1) || !inside(dx, dy - 1);
+
+        if filled || on_edge {
+          self.put_char(center.0 + dx, center.1 + dy, character);
+        }
+      }
+    }
+  }
+
+  fn circle(&mut self, center: (i32, i32), radius: i32, character: Character, filled: bool) {
+    self.ellipse(center, radius, radius, character, filled);
+  }
+
+  /// Replaces the 4-connected area of cells equal to the one at (col, row) with 'character'.
+  fn flood_fill(&mut self, col: i32, row: i32, character: Character) {
+    let Some(target) = self.char_at(col, row) else { return; };
+    if target == character { return; }
+
+    let mut to_fill = vec![(col, row)];
+
+    while let Some((x, y)) = to_fill.pop() {
+      if self.char_at(x, y) != Some(target) { continue; }
+
+      self.put_char(x, y, character);
+
+      to_fill.extend([(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)]);
+    }
+  }
+}
+
+impl<T> DrawShapes for T
+where T: DrawTarget + ?Sized {}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::DrawBuffer;
+
+  fn symbol(symbol: char) -> Character {
+    Character { symbol, ..Default::default() }
+  }
+
+  fn rows(db: &DrawBuffer) -> Vec<String> {
+    (0..db.height).map(|row| (0..db.width).map(|col| db.get_char(col, row).unwrap().symbol).collect()).collect()
+  }
+
+  #[test]
+  fn boxes_get_their_corners() {
+    let mut db = DrawBuffer::new(6, 4);
+
+    db.draw_box(Rect::new(0, 0, 5, 3), BorderStyle::Single, Character::default());
+
+    assert_eq!(rows(&db), ["┌───┐ ", "│   │ ", "└───┘ ", "      "]);
+  }
+
+  #[test]
+  fn every_border_style_puts_its_corners_in_place() {
+    for border in [BorderStyle::Single, BorderStyle::Double, BorderStyle::Heavy, BorderStyle::Ascii] {
+      let mut db = DrawBuffer::new(4, 4);
+      let [top_left, top_right, bottom_left, bottom_right, horizontal, vertical] = border.chars();
+
+      db.draw_box(Rect::new(1, 1, 3, 3), border, Character::default());
+
+      assert_eq!(rows(&db), [
+        "    ".to_string(),
+        format!(" {}{}{}", top_left, horizontal, top_right),
+        format!(" {} {}", vertical, vertical),
+        format!(" {}{}{}", bottom_left, horizontal, bottom_right),
+      ], "{:?}", border);
+    }
+  }
+
+  #[test]
+  fn boxes_too_thin_for_corners_are_outlined() {
+    let mut db = DrawBuffer::new(4, 3);
+    let template = symbol('*');
+
+    db.draw_box(Rect::new(0, 0, 1, 3), BorderStyle::Double, template);
+    db.draw_box(Rect::new(2, 1, 2, 1), BorderStyle::Double, template);
+
+    assert_eq!(rows(&db), ["*   ", "* **", "*   "]);
+  }
+
+  #[test]
+  fn boxes_take_their_colors_from_the_template() {
+    let mut db = DrawBuffer::new(3, 3);
+    let template = Character { color: crate::Color { r: 1, g: 2, b: 3 }, ..Default::default() };
+
+    db.draw_box(Rect::new(0, 0, 3, 3), BorderStyle::Single, template);
+
+    assert!(db.get_char(2, 2).unwrap().color == template.color);
+    assert!(db.get_char(1, 0).unwrap().color == template.color);
+  }
+
+  #[test]
+  fn boxes_are_clipped_to_the_target() {
+    let mut db = DrawBuffer::new(3, 3);
+
+    db.draw_box(Rect::new(-1, -1, 3, 3), BorderStyle::Ascii, Character::default());
+
+    assert_eq!(rows(&db), [" | ", "-+ ", "   "]);
+  }
+
+  #[test]
+  fn flood_fill_stops_at_borders() {
+    let mut db = DrawBuffer::new(5, 5);
+
+    db.outline_rect(Rect::new(0, 0, 4, 4), symbol('#'));
+    db.flood_fill(1, 1, symbol('.'));
+
+    assert_eq!(rows(&db), ["#### ", "#..# ", "#..# ", "#### ", "     "]);
+  }
+}
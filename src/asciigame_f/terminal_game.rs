@@ -1,151 +1,330 @@
-use crate::{GameState, Engine, Character};
+use crate::{GameState, Game, Backend, Character, Color, DrawBuffer, InputManager, KeyReleaseMode};
 
 use crossterm::{terminal, execute, cursor, queue, event::KeyCode,
   style::{SetBackgroundColor, SetForegroundColor, Print},
 };
 use std::time::{Duration, Instant};
-use std::io::{stdout};
+use std::io::{stdout, Write};
+use unicode_width::UnicodeWidthChar;
 use anyhow::Result;
 
-// TerminalWrapper. Holds the main game loop and manages all terminal interactions.
-pub struct TerminalGame<GS> 
-where GS: GameState {
-  // pub window_size: (u16, u16),
-  pub start_of_frame: Instant,
-
-  engine: Engine<GS>,
-  game_state: GS,
+// TerminalBackend. Manages all terminal interactions.
+pub struct TerminalBackend {
+  window_size: (u16, u16),
+  
+  // what the terminal is currently showing, so only the cells that changed get written
+  front_buffer: Vec<Character>,
+  front_size: (usize, usize),
+  offset: (i32, i32),
+  db_size: (usize, usize),
+  cursor: Option<(u16, u16)>,
+  color: Option<Color>,
+  color_back: Option<Color>,
+  
+  reports_releases: bool, // the terminal speaks the kitty keyboard protocol
 }
 
-impl<GS> TerminalGame<GS>
-where GS: GameState {
+impl TerminalBackend {
 
   pub fn new() -> Result<Self> {
-    let (cols, rows) = terminal::size()?;
-    
-    let mut eng = Engine::<GS>::new((cols as usize, rows as usize));
-    let gs = GameState::new(&mut eng);
-    
-    let s = Self {
-      // window_size: (cols, rows),
-      start_of_frame: Instant::now(),
-    
-      engine: eng,
-      game_state: gs, 
-    };
-    
-    Ok(s)
+    Ok(Self::with_size(terminal::size()?))
+  }
+
+  fn with_size(window_size: (u16, u16)) -> Self {
+    Self {
+      window_size,
+      
+      front_buffer: Vec::new(),
+      front_size: (0, 0),
+      offset: (0, 0),
+      db_size: (0, 0),
+      cursor: None,
+      color: None,
+      color_back: None,
+      
+      reports_releases: false,
+    }
   }
   
-  fn sync_frame(&mut self) {
-    use std::thread;
+  /// Forgets everything known about the terminal's state, so the next present writes all of it again.
+  fn invalidate(&mut self) {
+    self.front_size = (0, 0);
+    self.cursor = None;
+    self.color = None;
+    self.color_back = None;
+  }
 
-    let end_of_frame = Instant::now();
-    let passed_duration = end_of_frame.duration_since(self.start_of_frame);
+  /// Writes the cells that changed since the last frame to 'out'.
+  fn write_frame(&mut self, out: &mut impl Write, db: &mut DrawBuffer, offset: (i32, i32)) -> Result<()> {
+    let (screen_width, screen_height) = self.size();
     
-    let target_duration = std::time::Duration::from_nanos(1_000_000_000/self.engine.framerate);
-    let remaining_duration = target_duration.saturating_sub(passed_duration);
-    
-    thread::sleep(remaining_duration);
+    if self.front_size != (screen_width, screen_height) {
+      self.invalidate();
+      self.front_size = (screen_width, screen_height);
+      // nothing on screen matches the buffer anymore, so every cell gets written
+      self.front_buffer = vec![Character{ symbol: '\0', ..Default::default() }; screen_width * screen_height];
+      
+      queue!(out, terminal::Clear(crossterm::terminal::ClearType::All))?;
+    } else if !db.text_changed && offset == self.offset {
+      return Ok(());
+    }
+    self.offset = offset;
     
-    self.engine.frame_counter += 1;
-    self.start_of_frame += target_duration;
-  }
-  
-  fn draw(&mut self) -> Result<()> {
-    use std::io::{Write, stdout};
-  
-    let mut writing_handle = std::io::BufWriter::new(stdout().lock());
-  
-    let (db_width, db_height) = self.engine.db.get_size_usize();
+    let (db_width, db_height) = db.get_size_usize();
+    self.db_size = (db_width, db_height);
   
-    for y in 0..db_height {
-      for x in 0..db_width {
+    for y in 0..screen_height {
+      // set after a wide character, whose glyph also covers the next cell
+      let mut covered = false;
+      
+      for x in 0..screen_width {
+        
+        // screen cells outside of the DrawBuffer are left blank
+        let (db_x, db_y) = (x as i32 - offset.0, y as i32 - offset.1);
+        let c: Character = if db_x >= 0 && db_y >= 0 && (db_x as usize) < db_width && (db_y as usize) < db_height {
+          db[(db_x as usize, db_y as usize)]
+        } else {
+          Character::default()
+        };
+        
+        let front = &mut self.front_buffer[y * screen_width + x];
+        
+        if covered {
+          // whatever the terminal shows here isn't this cell, so it's written again once it's uncovered
+          *front = Character{ symbol: '\0', ..Default::default() };
+          covered = false;
+          continue;
+        }
+        
+        let char_width = c.symbol.width().unwrap_or(0);
+        covered = char_width > 1;
+        
+        if *front == c { continue; }
+        *front = c;
 
         let x_u16: u16 = u16::try_from(x)?;
         let y_u16: u16 = u16::try_from(y)?;
         
-        let c: &Character = &self.engine.db[(x, y)];
+        // adjacent changed cells are written as one run, without moving the cursor in between
+        if self.cursor != Some((x_u16, y_u16)) {
+          queue!(out, cursor::MoveTo(x_u16, y_u16))?;
+        }
+        if self.color != Some(c.color) {
+          queue!(out, SetForegroundColor(c.color.into()))?;
+          self.color = Some(c.color);
+        }
+        if self.color_back != Some(c.color_back) {
+          queue!(out, SetBackgroundColor(c.color_back.into()))?;
+          self.color_back = Some(c.color_back);
+        }
         
-        queue!(
-          writing_handle,
-          cursor::MoveTo(x_u16, y_u16),
-          SetForegroundColor(c.color.into()),
-          SetBackgroundColor(c.color_back.into()),
-          Print(c.symbol),
-        )?;
+        queue!(out, Print(c.symbol))?;
+        
+        // the cursor position after writing into the last column depends on the terminal,
+        // and so does the width of anything that isn't a plain single-width character
+        self.cursor = if char_width == 1 && x + 1 < screen_width { Some((x_u16 + 1, y_u16)) } else { None };
         
       }
         
     }
     
-    writing_handle.flush()?;
-    
-    Ok(())
-  }
-  
-  fn process_events(&mut self) -> Result<()> {
-    use crossterm::event::{poll, read, Event, KeyEventKind, KeyCode};
-    use std::time::Duration;
-    
-    self.engine.inp_man.cycle_events();
-    
-    while poll(Duration::ZERO)? {
-      // It's guaranteed that the 'read()' won't block when the 'poll()' function returns 'true' // match read()? { // Event::FocusGained => println!("FocusGained"), // Event::FocusLost => println!("FocusLost"), // Event::Mouse(event) => println!("{:?}", event), // #[cfg(feature = "bracketed-paste")] // Event::Paste(data) => println!("Pasted {:?}", data), // Event::Resize(width, height) => println!("New size {}x{}", width, height), // }
-      if let Event::Key(key_event) = read()? {
-      
-        self.engine.inp_man.process_crossterm_key(key_event);
-        
-      }
-      
-    }
+    db.text_changed = false;
     
     Ok(())
-  
   }
-  
-  fn game_loop(&mut self) -> Result<()> {
-    self.start_of_frame = Instant::now();
-        
-    // Running the game
-    while self.game_state.should_run() {
-      self.process_events()?;
-      self.engine.inp_dis.dispatch(&mut self.engine.inp_man, &mut self.game_state);
-      
-      self.game_state.update(&mut self.engine);
-      
-      self.game_state.draw(&mut self.engine);
-      self.draw()?;
 
-      self.sync_frame();
-    }
+}
+
+impl Backend for TerminalBackend {
+
+  fn init(&mut self) -> Result<()> {
+    self.invalidate();
     
-    Ok(())
-  }
-  
-  pub fn run(&mut self) -> Result<()> {
-    // 1. Setup
     terminal::enable_raw_mode()?;
+    // most terminals ignore the enhancement flags below and never send releases
+    self.reports_releases = terminal::supports_keyboard_enhancement().unwrap_or(false);
+    
     execute!(stdout(),
       terminal::EnterAlternateScreen,
       terminal::Clear(crossterm::terminal::ClearType::All),
       cursor::Hide,
+      crossterm::event::EnableMouseCapture,
+      crossterm::event::EnableBracketedPaste,
       crossterm::event::PushKeyboardEnhancementFlags(
-        crossterm::event::KeyboardEnhancementFlags::REPORT_EVENT_TYPES
+        crossterm::event::KeyboardEnhancementFlags::REPORT_EVENT_TYPES |
+        crossterm::event::KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES // tells keypad keys apart
       ),
     )?;
-    
-    let loop_result = self.game_loop();
-    
-    // 3. Teardown
+
+    Ok(())
+  }
+
+  fn shutdown(&mut self) -> Result<()> {
     let _ = execute!(stdout(),
       crossterm::event::PopKeyboardEnhancementFlags,
+      crossterm::event::DisableBracketedPaste,
+      crossterm::event::DisableMouseCapture,
       cursor::Show,
       terminal::LeaveAlternateScreen,
     );
     let _ = terminal::disable_raw_mode();
 
-    loop_result
+    Ok(())
   }
 
+  fn size(&self) -> (usize, usize) {
+    (self.window_size.0 as usize, self.window_size.1 as usize)
+  }
+
+  fn key_release_mode(&self) -> KeyReleaseMode {
+    match self.reports_releases {
+      true => KeyReleaseMode::Reported,
+      false => KeyReleaseMode::Synthesized,
+    }
+  }
+
+  fn pump_input(&mut self, inp_man: &mut InputManager) -> Result<()> {
+    use crossterm::event::{poll, read, Event, KeyEventKind, KeyCode};
+    use std::time::Duration;
+
+    while poll(Duration::ZERO)? {
+      // It's guaranteed that the 'read()' won't block when the 'poll()' function returns 'true' // match read()? { // Event::FocusGained => println!("FocusGained"), // Event::FocusLost => println!("FocusLost"), // Event::Mouse(event) => println!("{:?}", event), // #[cfg(feature = "bracketed-paste")] // Event::Paste(data) => println!("Pasted {:?}", data), // Event::Resize(width, height) => println!("New size {}x{}", width, height), // }
+      match read()? {
+        Event::Key(key_event) => inp_man.process_crossterm_key(key_event),
+        Event::Mouse(mouse_event) => inp_man.process_crossterm_mouse(mouse_event, self.offset, self.db_size),
+        Event::Paste(text) => inp_man.insert_text(&text),
+        Event::Resize(cols, rows) => self.window_size = (cols, rows),
+        _ => (),
+      }
+
+    }
+
+    Ok(())
+  }
+
+  fn present(&mut self, db: &mut DrawBuffer, offset: (i32, i32)) -> Result<()> {
+    let mut writing_handle = std::io::BufWriter::new(stdout().lock());
+
+    self.write_frame(&mut writing_handle, db, offset)?;
+    writing_handle.flush()?;
+
+    Ok(())
+  }
+
+}
+
+/// TerminalGame. Runs a GameState inside the terminal it was started from.
+pub type TerminalGame<GS> = Game<GS, TerminalBackend>;
+
+impl<GS> Game<GS, TerminalBackend>
+where GS: GameState {
+
+  pub fn new() -> Result<Self> {
+    Self::with_backend(TerminalBackend::new()?)
+  }
+
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn symbol(symbol: char) -> Character {
+    Character { symbol, ..Default::default() }
+  }
+
+  fn move_to(col: u16, row: u16) -> String {
+    format!("\x1b[{};{}H", row + 1, col + 1)
+  }
+
+  fn frame(backend: &mut TerminalBackend, db: &mut DrawBuffer) -> String {
+    let mut out = Vec::new();
+    backend.write_frame(&mut out, db, (0, 0)).unwrap();
+
+    String::from_utf8(out).unwrap()
+  }
+
+  #[test]
+  fn the_first_frame_writes_every_cell() {
+    let mut backend = TerminalBackend::with_size((3, 2));
+    let mut db = DrawBuffer::new(3, 2);
+
+    let out = frame(&mut backend, &mut db);
+
+    assert!(out.starts_with("\x1b[2J"), "{:?}", out);
+    assert_eq!(out.matches(' ').count(), 6, "{:?}", out);
+  }
+
+  #[test]
+  fn unchanged_frames_write_nothing() {
+    let mut backend = TerminalBackend::with_size((3, 2));
+    let mut db = DrawBuffer::new(3, 2);
+    frame(&mut backend, &mut db);
+
+    assert_eq!(frame(&mut backend, &mut db), "");
+
+    // redrawing the same cells marks the buffer as changed only if something differs
+    db.set_char(1, 1, symbol(' '));
+    db.text_changed = true;
+    db.compose();
+    assert_eq!(frame(&mut backend, &mut db), "");
+  }
+
+  #[test]
+  fn only_changed_cells_are_written() {
+    let mut backend = TerminalBackend::with_size((4, 3));
+    let mut db = DrawBuffer::new(4, 3);
+    frame(&mut backend, &mut db);
+
+    db.set_char(2, 1, symbol('a'));
+    db.set_char(0, 2, symbol('b'));
+    db.set_char(1, 2, symbol('c'));
+    db.compose();
+
+    // the colors didn't change and adjacent cells share a single cursor move
+    assert_eq!(frame(&mut backend, &mut db), format!("{}a{}bc", move_to(2, 1), move_to(0, 2)));
+  }
+
+  #[test]
+  fn wide_characters_cover_the_next_cell() {
+    let mut backend = TerminalBackend::with_size((4, 1));
+    let mut db = DrawBuffer::new(4, 1);
+    frame(&mut backend, &mut db);
+
+    db.set_char(0, 0, symbol('日'));
+    db.set_char(1, 0, symbol('x')); // hidden under the wide character, so never printed
+    db.set_char(2, 0, symbol('a'));
+    db.compose();
+
+    // the terminal's cursor after a wide character can't be trusted, so 'a' gets its own move
+    assert_eq!(frame(&mut backend, &mut db), format!("{}日{}a", move_to(0, 0), move_to(2, 0)));
+  }
+
+  #[test]
+  fn cells_uncovered_by_a_wide_character_are_written_again() {
+    let mut backend = TerminalBackend::with_size((4, 1));
+    let mut db = DrawBuffer::new(4, 1);
+    db.set_char(0, 0, symbol('日'));
+    db.compose();
+    frame(&mut backend, &mut db);
+
+    db.set_char(0, 0, symbol('a'));
+    db.compose();
+
+    // the blank cell under the wide character's right half was never changed in the buffer
+    assert_eq!(frame(&mut backend, &mut db), format!("{}a ", move_to(0, 0)));
+  }
+
+  #[test]
+  fn resizing_the_terminal_writes_everything_again() {
+    let mut backend = TerminalBackend::with_size((3, 1));
+    let mut db = DrawBuffer::new(3, 1);
+    frame(&mut backend, &mut db);
+
+    backend.window_size = (2, 1);
+    let out = frame(&mut backend, &mut db);
+
+    assert!(out.starts_with("\x1b[2J"), "{:?}", out);
+    assert_eq!(out.matches(' ').count(), 2, "{:?}", out);
+  }
 }
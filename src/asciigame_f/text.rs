@@ -0,0 +1,237 @@
+use crate::{Character, Color, Transparency, DrawTarget};
+
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// Horizontal placement of a line of text inside its width.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Align {
+  #[default]
+  Left,
+  Center,
+  Right,
+}
+
+/// How printed text looks.
+#[derive(Clone, Copy, PartialEq)]
+pub struct TextStyle {
+  pub color: Color,
+  pub color_back: Color,
+  pub transparency: Transparency,
+  pub align: Align,
+}
+
+impl Default for TextStyle {
+  fn default() -> Self {
+    let base = Character::default();
+
+    TextStyle { color: base.color, color_back: base.color_back, transparency: base.transparency, align: Align::Left }
+  }
+}
+
+impl TextStyle {
+  pub fn new(color: Color, color_back: Color) -> Self {
+    TextStyle { color, color_back, ..Default::default() }
+  }
+
+  pub fn align(self, align: Align) -> Self {
+    TextStyle { align, ..self }
+  }
+
+  pub fn transparency(self, transparency: Transparency) -> Self {
+    TextStyle { transparency, ..self }
+  }
+
+  fn character(&self, symbol: char) -> Character {
+    Character { symbol, color: self.color, color_back: self.color_back, transparency: self.transparency }
+  }
+}
+
+pub const ELLIPSIS: char = '…';
+
+/// Width of the text in cells.
+pub fn text_width(text: &str) -> usize {
+  UnicodeWidthStr::width(text)
+}
+
+/// Cuts the text down to 'max_width' cells, ending it with an ellipsis if anything was cut.
+pub fn truncate(text: &str, max_width: usize) -> String {
+  if text_width(text) <= max_width {
+    return text.to_string();
+  }
+  if max_width == 0 {
+    return String::new();
+  }
+
+  let mut result = String::new();
+  let mut width = 0;
+
+  for c in text.chars() {
+    let char_width = c.width().unwrap_or(0);
+    if width + char_width > max_width - 1 { break; }
+
+    result.push(c);
+    width += char_width;
+  }
+  result.push(ELLIPSIS);
+
+  result
+}
+
+/// Splits the text into lines of at most 'width' cells, breaking at whitespace when possible.
+/// Newlines in the text always start a new line.
+pub fn wrap(text: &str, width: usize) -> Vec<String> {
+  let mut lines = Vec::new();
+  if width == 0 { return lines; }
+
+  for paragraph in text.split('\n') {
+    let mut line = String::new();
+    let mut line_width = 0;
+
+    for word in paragraph.split_whitespace() {
+      // start a new line if the word doesn't fit behind the current one
+      if line_width > 0 && line_width + 1 + text_width(word) > width {
+        lines.push(std::mem::take(&mut line));
+        line_width = 0;
+      }
+
+      if line_width > 0 {
+        line.push(' ');
+        line_width += 1;
+      }
+
+      // words longer than a whole line get split wherever they hit the edge
+      for c in word.chars() {
+        let char_width = c.width().unwrap_or(0);
+
+        if line_width > 0 && line_width + char_width > width {
+          lines.push(std::mem::take(&mut line));
+          line_width = 0;
+        }
+
+        line.push(c);
+        line_width += char_width;
+      }
+    }
+
+    lines.push(line);
+  }
+
+  lines
+}
+
+/// Text drawing on anything that implements DrawTarget. Everything is clipped to the target.
+pub trait DrawText: DrawTarget {
+  /// Prints a single line starting at (col, row), ignoring alignment. Returns the width printed, in cells.
+  fn print(&mut self, col: i32, row: i32, text: &str, style: &TextStyle) -> usize {
+    let mut x = col;
+
+    for c in text.chars() {
+      let char_width = c.width().unwrap_or(0);
+      if char_width == 0 { continue; }
+
+      // wide characters take up the following cell as well, which is left untouched
+      self.put_char(x, row, style.character(c));
+      x += char_width as i32;
+    }
+
+    (x - col) as usize
+  }
+
+  /// Prints a single line aligned inside 'width' cells starting at 'col', truncated with an ellipsis if it doesn't fit.
+  fn print_aligned(&mut self, col: i32, row: i32, width: usize, text: &str, style: &TextStyle) -> usize {
+    let line = truncate(text, width);
+    let line_width = text_width(&line);
+
+    let offset = match style.align {
+      Align::Left => 0,
+      Align::Center => (width - line_width) / 2,
+      Align::Right => width - line_width,
+    };
+
+    self.print(col + offset as i32, row, &line, style)
+  }
+
+  /// Word-wraps the text to 'width' cells and prints it line by line, each one aligned. Returns the number of lines.
+  fn print_wrapped(&mut self, col: i32, row: i32, width: usize, text: &str, style: &TextStyle) -> usize {
+    let lines = wrap(text, width);
+
+    for (index, line) in lines.iter().enumerate() {
+      self.print_aligned(col, row + index as i32, width, line, style);
+    }
+
+    lines.len()
+  }
+}
+
+impl<T> DrawText for T
+where T: DrawTarget + ?Sized {}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::DrawBuffer;
+
+  fn row(db: &DrawBuffer, row: usize) -> String {
+    (0..db.width).map(|col| db.get_char(col, row).unwrap().symbol).collect()
+  }
+
+  #[test]
+  fn wrapping_breaks_between_words() {
+    assert_eq!(wrap("the quick brown fox", 10), ["the quick", "brown fox"]);
+    assert_eq!(wrap("the quick brown fox", 9), ["the quick", "brown fox"]);
+    assert_eq!(wrap("the   quick\nfox", 20), ["the quick", "fox"], "whitespace collapses, newlines stay");
+  }
+
+  #[test]
+  fn overlong_words_are_split_at_the_edge() {
+    assert_eq!(wrap("abcdefghij", 4), ["abcd", "efgh", "ij"]);
+    assert_eq!(wrap("ab cdefghij k", 4), ["ab", "cdef", "ghij", "k"]);
+    assert_eq!(wrap("日本語", 4), ["日本", "語"], "wide characters aren't cut in half");
+  }
+
+  #[test]
+  fn truncation_ends_with_an_ellipsis() {
+    assert_eq!(truncate("hello", 5), "hello");
+    assert_eq!(truncate("hello world", 5), "hell…");
+    assert_eq!(truncate("hello", 1), "…");
+    assert_eq!(truncate("hello", 0), "");
+    assert_eq!(truncate("日本語", 4), "日…");
+    assert_eq!(text_width(&truncate("日本語", 4)), 3);
+  }
+
+  #[test]
+  fn each_alignment_places_the_line() {
+    let mut db = DrawBuffer::new(8, 4);
+    let style = TextStyle::default();
+
+    db.print_aligned(0, 0, 8, "abc", &style.align(Align::Left));
+    db.print_aligned(0, 1, 8, "abc", &style.align(Align::Center));
+    db.print_aligned(0, 2, 8, "abc", &style.align(Align::Right));
+    db.print_aligned(0, 3, 8, "abcdefghij", &style.align(Align::Right));
+
+    assert_eq!(row(&db, 0), "abc     ");
+    assert_eq!(row(&db, 1), "  abc   ");
+    assert_eq!(row(&db, 2), "     abc");
+    assert_eq!(row(&db, 3), "abcdefg…");
+  }
+
+  #[test]
+  fn wrapped_text_is_aligned_line_by_line() {
+    let mut db = DrawBuffer::new(7, 3);
+
+    let lines = db.print_wrapped(0, 0, 7, "one two three", &TextStyle::default().align(Align::Center));
+
+    assert_eq!(lines, 2);
+    assert_eq!(row(&db, 0), "one two");
+    assert_eq!(row(&db, 1), " three ");
+    assert_eq!(row(&db, 2), "       ");
+  }
+
+  #[test]
+  fn printing_is_clipped_to_the_target() {
+    let mut db = DrawBuffer::new(4, 1);
+
+    assert_eq!(db.print(-2, 0, "abcdef", &TextStyle::default()), 6);
+    assert_eq!(row(&db, 0), "cdef");
+  }
+}
@@ -0,0 +1,71 @@
+/// What the player typed while the InputManager is in text input mode (see 'InputManager::start_text_input').
+/// Unlike key states, these are the real characters: shifted, composed, pasted or committed by an IME.
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub enum TextEvent {
+  Insert(String),
+  Backspace,
+  Delete,
+  Left,
+  Right,
+  Home,
+  End,
+  Submit, // Enter
+  Cancel, // Esc
+}
+
+/// A single line of editable text with a cursor, driven by TextEvents. For name entry, prompts and the like.
+#[derive(Default, Clone, Debug)]
+pub struct TextField {
+  pub text: String,
+  pub cursor: usize, // in chars, 0..=text.chars().count()
+  pub max_len: Option<usize>, // in chars
+}
+
+impl TextField {
+  pub fn new(text: &str) -> Self {
+    Self {
+      text: text.to_string(),
+      cursor: text.chars().count(),
+      max_len: None,
+    }
+  }
+
+  pub fn max_len(mut self, max_len: usize) -> Self {
+    self.max_len = Some(max_len);
+
+    self
+  }
+
+  /// Applies an editing event. Submit and Cancel don't change the text, the game decides what they mean.
+  pub fn apply(&mut self, event: &TextEvent) {
+    let len = self.text.chars().count();
+    self.cursor = self.cursor.min(len);
+
+    match event {
+      TextEvent::Insert(inserted) => {
+        let room = self.max_len.map_or(usize::MAX, |max_len| max_len.saturating_sub(len));
+        let inserted: String = inserted.chars().filter(|c| !c.is_control()).take(room).collect();
+
+        let at = self.byte_index(self.cursor);
+        self.text.insert_str(at, &inserted);
+        self.cursor += inserted.chars().count();
+      },
+      TextEvent::Backspace if self.cursor > 0 => {
+        self.cursor -= 1;
+        self.text.remove(self.byte_index(self.cursor));
+      },
+      TextEvent::Delete if self.cursor < len => {
+        self.text.remove(self.byte_index(self.cursor));
+      },
+      TextEvent::Left => self.cursor = self.cursor.saturating_sub(1),
+      TextEvent::Right => self.cursor = (self.cursor + 1).min(len),
+      TextEvent::Home => self.cursor = 0,
+      TextEvent::End => self.cursor = len,
+      _ => (),
+    }
+  }
+
+  fn byte_index(&self, char_index: usize) -> usize {
+    self.text.char_indices().nth(char_index).map_or(self.text.len(), |(index, _)| index)
+  }
+}
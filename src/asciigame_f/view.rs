@@ -0,0 +1,229 @@
+use crate::{Character, DrawBuffer};
+
+/// A rectangle of cells. 'x' and 'y' are the top-left corner and may be negative.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Rect {
+  pub x: i32,
+  pub y: i32,
+  pub width: usize,
+  pub height: usize,
+}
+
+impl Rect {
+  pub fn new(x: i32, y: i32, width: usize, height: usize) -> Self {
+    Rect { x, y, width, height }
+  }
+
+  pub fn contains(&self, col: i32, row: i32) -> bool {
+    col >= self.x && row >= self.y &&
+    col < self.x + self.width as i32 && row < self.y + self.height as i32
+  }
+
+  /// The part of both rectangles that overlaps. Empty rectangles have a zero width or height.
+  pub fn intersect(&self, other: &Rect) -> Rect {
+    let left = self.x.max(other.x);
+    let top = self.y.max(other.y);
+    let right = (self.x + self.width as i32).min(other.x + other.width as i32);
+    let bottom = (self.y + self.height as i32).min(other.y + other.height as i32);
+
+    Rect {
+      x: left,
+      y: top,
+      width: (right - left).max(0) as usize,
+      height: (bottom - top).max(0) as usize,
+    }
+  }
+}
+
+/// Anything that can be drawn on cell by cell. The draw helpers are written against this trait,
+/// so they work the same on the whole DrawBuffer and on any View of it.
+pub trait DrawTarget {
+  /// Size in cells.
+  fn size(&self) -> (usize, usize);
+
+  /// Writes a Character at local coordinates. Writes outside of the bounds are dropped.
+  fn put_char(&mut self, col: i32, row: i32, character: Character);
+
+  /// Reads the Character at local coordinates. None outside of the bounds.
+  fn char_at(&self, col: i32, row: i32) -> Option<Character>;
+
+  /// A View on the given part of this target, in local coordinates. It's clipped to this target's bounds.
+  fn view(&mut self, rect: Rect) -> View<'_>;
+
+  fn fill(&mut self, character: Character) {
+    let (width, height) = self.size();
+
+    for row in 0..height as i32 {
+      for col in 0..width as i32 {
+        self.put_char(col, row, character);
+      }
+    }
+  }
+}
+
+impl DrawTarget for DrawBuffer {
+  fn size(&self) -> (usize, usize) {
+    (self.width, self.height)
+  }
+
+  fn put_char(&mut self, col: i32, row: i32, character: Character) {
+    if col < 0 || row < 0 { return; }
+
+    self.set_char(col as usize, row as usize, character);
+  }
+
+  fn char_at(&self, col: i32, row: i32) -> Option<Character> {
+    if col < 0 || row < 0 { return None; }
+
+    self.get_char(col as usize, row as usize)
+  }
+
+  fn view(&mut self, rect: Rect) -> View<'_> {
+    let bounds = Rect::new(0, 0, self.width, self.height);
+
+    View {
+      origin: (rect.x, rect.y),
+      clip: rect.intersect(&bounds),
+      db: self,
+    }
+  }
+}
+
+/// A rectangular window into a DrawBuffer with its own local coordinates. Writes are clipped to its bounds.
+pub struct View<'a> {
+  db: &'a mut DrawBuffer,
+  origin: (i32, i32), // where the local (0, 0) is in the DrawBuffer, it may lie outside of the clip
+  clip: Rect,         // in DrawBuffer coordinates
+}
+
+impl View<'_> {
+  /// The part of the DrawBuffer this View can write to, in DrawBuffer coordinates.
+  pub fn bounds(&self) -> Rect {
+    self.clip
+  }
+
+  /// Moves the local coordinates so that (col, row) lands on the View's top-left cell. The clip stays where it is.
+  pub fn scrolled(mut self, col: i32, row: i32) -> Self {
+    self.origin = (self.origin.0 - col, self.origin.1 - row);
+
+    self
+  }
+}
+
+impl DrawTarget for View<'_> {
+  fn size(&self) -> (usize, usize) {
+    let right = self.clip.x + self.clip.width as i32 - self.origin.0;
+    let bottom = self.clip.y + self.clip.height as i32 - self.origin.1;
+
+    (right.max(0) as usize, bottom.max(0) as usize)
+  }
+
+  fn put_char(&mut self, col: i32, row: i32, character: Character) {
+    let (x, y) = (col + self.origin.0, row + self.origin.1);
+
+    if self.clip.contains(x, y) {
+      self.db.set_char(x as usize, y as usize, character);
+    }
+  }
+
+  fn char_at(&self, col: i32, row: i32) -> Option<Character> {
+    let (x, y) = (col + self.origin.0, row + self.origin.1);
+
+    if !self.clip.contains(x, y) { return None; }
+
+    self.db.get_char(x as usize, y as usize)
+  }
+
+  fn view(&mut self, rect: Rect) -> View<'_> {
+    let absolute = Rect::new(rect.x + self.origin.0, rect.y + self.origin.1, rect.width, rect.height);
+
+    View {
+      origin: (absolute.x, absolute.y),
+      clip: absolute.intersect(&self.clip),
+      db: self.db,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn symbol(symbol: char) -> Character {
+    Character { symbol, ..Default::default() }
+  }
+
+  fn rows(db: &DrawBuffer) -> Vec<String> {
+    (0..db.height).map(|row| (0..db.width).map(|col| db.get_char(col, row).unwrap().symbol).collect()).collect()
+  }
+
+  #[test]
+  fn views_clip_their_writes() {
+    let mut db = DrawBuffer::new(6, 4);
+    let mut view = db.view(Rect::new(1, 1, 3, 2));
+
+    assert_eq!(view.size(), (3, 2));
+    view.fill(symbol('#'));
+    view.put_char(-1, 0, symbol('x'));
+    view.put_char(3, 0, symbol('x'));
+    view.put_char(0, 2, symbol('x'));
+    assert!(view.char_at(3, 0).is_none());
+    assert_eq!(view.char_at(2, 1).map(|c| c.symbol), Some('#'));
+
+    assert_eq!(rows(&db), ["      ", " ###  ", " ###  ", "      "]);
+  }
+
+  #[test]
+  fn views_past_the_buffer_are_clipped_to_it() {
+    let mut db = DrawBuffer::new(4, 3);
+    let mut view = db.view(Rect::new(-2, 1, 4, 5));
+
+    assert_eq!(view.bounds(), Rect::new(0, 1, 2, 2));
+    view.put_char(0, 0, symbol('x')); // off the buffer's left edge
+    view.put_char(2, 0, symbol('a'));
+    view.put_char(3, 1, symbol('b'));
+
+    assert_eq!(rows(&db), ["    ", "a   ", " b  "]);
+  }
+
+  #[test]
+  fn nested_views_add_up_their_offsets() {
+    let mut db = DrawBuffer::new(8, 6);
+    let mut outer = db.view(Rect::new(2, 1, 5, 4));
+    let mut inner = outer.view(Rect::new(1, 1, 2, 2));
+
+    assert_eq!(inner.bounds(), Rect::new(3, 2, 2, 2));
+    inner.put_char(0, 0, symbol('a'));
+    inner.put_char(1, 1, symbol('b'));
+
+    assert_eq!(rows(&db)[2], "   a    ");
+    assert_eq!(rows(&db)[3], "    b   ");
+  }
+
+  #[test]
+  fn nested_views_stay_inside_their_parent() {
+    let mut db = DrawBuffer::new(8, 6);
+    let mut outer = db.view(Rect::new(2, 1, 3, 3));
+    let mut inner = outer.view(Rect::new(1, 1, 5, 5));
+
+    assert_eq!(inner.bounds(), Rect::new(3, 2, 2, 2));
+    inner.fill(symbol('#'));
+    inner.put_char(2, 0, symbol('x')); // inside the inner rect but past the outer one
+
+    assert_eq!(rows(&db), ["        ", "        ", "   ##   ", "   ##   ", "        ", "        "]);
+  }
+
+  #[test]
+  fn scrolled_views_move_the_contents_not_the_clip() {
+    let mut db = DrawBuffer::new(6, 3);
+    let mut view = db.view(Rect::new(1, 0, 3, 3)).scrolled(10, 0);
+
+    assert_eq!(view.bounds(), Rect::new(1, 0, 3, 3));
+    view.put_char(10, 1, symbol('a'));
+    view.put_char(12, 1, symbol('b'));
+    view.put_char(9, 1, symbol('x'));
+    view.put_char(13, 1, symbol('x'));
+
+    assert_eq!(rows(&db)[1], " a b  ");
+  }
+}
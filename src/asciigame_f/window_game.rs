@@ -4,10 +4,11 @@ use winit::{
   application::ApplicationHandler,
   event::*,
   event_loop::{ActiveEventLoop, EventLoop},
+  keyboard::ModifiersState,
   window::{Window, WindowId},
 };
 
-use crate::{GameState, Engine, Character};
+use crate::{GameState, Game, Backend, Character, DrawBuffer, InputManager, translate_winit_modifiers};
 
 use std::time::{Duration, Instant};
 use anyhow::Result;
@@ -38,6 +39,10 @@ pub struct WindowState {
   metrics: glyphon::Metrics, // dictates the font size. needs to be saved for consistency across resizes.
     
   span_cache: Vec<(String, glyphon::Attrs<'static>)>,
+  
+  // where the last frame's grid was drawn, in pixels, for turning cursor positions into cells
+  grid_origin: (f32, f32),
+  grid_size: (usize, usize),
 }
 
 impl WindowState {
@@ -176,6 +181,9 @@ impl WindowState {
       metrics: glyphon::Metrics{ font_size: 32.0, line_height: 32.0 },
       
       span_cache: Vec::with_capacity(5000),
+      
+      grid_origin: (0.0, 0.0),
+      grid_size: (0, 0),
     })
   }
   
@@ -218,6 +226,16 @@ impl WindowState {
     }
   }
   
+  /// The DrawBuffer cell under a pixel position, accounting for the letterbox around the grid.
+  pub fn pixel_to_cell(&self, x: f64, y: f64) -> Option<(i32, i32)> {
+    let col = ((x as f32 - self.grid_origin.0) / self.metrics.font_size).floor() as i32;
+    let row = ((y as f32 - self.grid_origin.1) / self.metrics.line_height).floor() as i32;
+    
+    let inside = col >= 0 && row >= 0 && (col as usize) < self.grid_size.0 && (row as usize) < self.grid_size.1;
+    
+    if inside { Some((col, row)) } else { None }
+  }
+  
   pub fn render(&mut self) {
     self.window.request_redraw();
   }
@@ -238,46 +256,9 @@ impl WindowState {
     Ok(font_name.to_string())
   }
   
-}
-
-/// WindowGame. An abstraction layer between the User and the Engine. Is responsible for the game loop, rendering and input catching.
-pub struct WindowGame<GS>
-where GS: GameState {
-  window_state: Option<WindowState>,  
-  
-  // game stuff
-  current_time: Instant,
-  fixed_time_step: Duration,
-  accumulator: Duration,
-  
-  engine: Engine<GS>,
-  game_state: GS,
+  pub fn draw(&mut self, db: &mut DrawBuffer) -> Result<(), wgpu::SurfaceError> {
   
-}
-
-impl<GS> WindowGame<GS>
-where GS: GameState {
-  pub fn new() -> Result<Self> {    
-    let (cols, rows) = (32, 18);
-    
-    let mut eng = Engine::<GS>::new((cols, rows));
-    let gs = GameState::new(&mut eng);
-    
-    Ok(Self{
-      window_state: None,
-      
-      current_time: Instant::now(),
-      fixed_time_step: Duration::new(0,0),
-      accumulator: Duration::new(0,0),
-      
-      engine: eng,
-      game_state: gs,
-    })
-  }
-  
-  fn draw(&mut self) -> Result<(), wgpu::SurfaceError> {
-  
-    let ws = self.window_state.as_mut().unwrap();
+    let ws = self;
     
     let drawable = ws.surface.get_current_texture()?; // SurfaceTexture
     let image_view_descriptor = TextureViewDescriptor::default();
@@ -288,18 +269,21 @@ where GS: GameState {
     };
     let mut command_encoder = ws.device.create_command_encoder(&command_enconder_descriptor); // CommandEncoder
     
-    let (db_width, db_height) = self.engine.db.get_size_usize();
+    let (db_width, db_height) = db.get_size_usize();
     
     let window_width = ws.config.width as f32;
     let window_height = ws.config.height as f32;
     
     let left_offset = ( window_width - ws.metrics.font_size * db_width as f32 ) / 2.0;
     let top_offset = ( window_height - ws.metrics.line_height * db_height as f32 ) / 2.0;
+    
+    ws.grid_origin = (left_offset, top_offset);
+    ws.grid_size = (db_width, db_height);
   
     // println!("draw");
     
     // glyphon preparation
-    if self.engine.db.text_changed {
+    if db.text_changed {
       
       // println!("redraw");
       
@@ -309,7 +293,7 @@ where GS: GameState {
       for row in 0..=db_height-1 {
         for col in 0..=db_width-1 {
         
-          let char_struct = &self.engine.db[(col,row)];
+          let char_struct = &db[(col,row)];
           // Convert the Engine Color to Glyphon Color
           let g_color = glyphon::Color::rgb(
             char_struct.color.r,
@@ -361,7 +345,7 @@ where GS: GameState {
       for row_idx in 0..=db_height-1 {
         for col_idx in 0..=db_width-1 {
         
-          let char_struct = &self.engine.db[(col_idx,row_idx)];
+          let char_struct = &db[(col_idx,row_idx)];
           
           // skip if background is pure black (optimization)
           if char_struct.color_back.r == 0 && char_struct.color_back.g == 0 && char_struct.color_back.b == 0 {
@@ -397,7 +381,7 @@ where GS: GameState {
       
       // print!("redraw");
       
-      self.engine.db.text_changed = false;
+      db.text_changed = false;
     }
     
     let text_area = glyphon::TextArea {
@@ -477,31 +461,30 @@ where GS: GameState {
     Ok(())
   }
   
-  fn game_step(&mut self, event_loop: &ActiveEventLoop) {
-    // game loop logic is placed here
-    
-    self.engine.frame_counter += 1;
-    
-    if !(self.game_state.should_run()) {
-      event_loop.exit();
-      
-      return;
-    }
-    
-    self.engine.inp_dis.dispatch(&mut self.engine.inp_man, &mut self.game_state);
-    
-    self.game_state.update(&mut self.engine);
-    
-    self.engine.inp_man.cycle_events();
-    
-  }
   
 }
 
-impl<GS> ApplicationHandler for WindowGame<GS>
-where GS: GameState {
+// WindowApp. Receives the winit events while the WindowBackend pumps the event loop.
+struct WindowApp {
+  window_state: Option<WindowState>,
   
-  fn resumed(&mut self, event_loop: &ActiveEventLoop) {    
+  key_events: Vec<(winit::event::KeyEvent, ModifiersState)>, // with the modifiers held when it arrived
+  modifiers: ModifiersState,
+  ime_commits: Vec<String>,
+  ime_allowed: bool,
+  mouse_buttons: Vec<(winit::event::MouseButton, winit::event::ElementState)>,
+  cursor_pixels: Option<(f64, f64)>,
+  wheel_lines: (f32, f32),
+  needs_resize: bool,
+  grid_size: (usize, usize),
+  quit: bool,
+}
+
+impl ApplicationHandler for WindowApp {
+  
+  fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+    if self.window_state.is_some() { return; }
+    
     let attributes = Window::default_attributes()
       .with_title("ASCII Engine")
       .with_transparent(false)
@@ -512,70 +495,180 @@ where GS: GameState {
     
     let state_result = pollster::block_on(WindowState::new(window.into()));
     match state_result {
-      Ok(win_state) => self.window_state = Some(win_state),
+      Ok(win_state) => {
+        self.window_state = Some(win_state);
+        self.needs_resize = true;
+      },
       Err(e) => {
         eprintln!("Error initializing GPU: {}", e);
+        self.quit = true;
       },
     };
   }
   
-  fn window_event(&mut self, event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
+  fn window_event(&mut self, _event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
     match event {
       WindowEvent::CloseRequested => {
-        println!("The close button was pressed.");
-        event_loop.exit();
+        self.quit = true;
       },
       WindowEvent::Resized(new_size) => {
         if let Some(ws) = &mut self.window_state {
-          ws.resize(new_size, self.engine.db.height, self.engine.db.width);
-          self.engine.db.text_changed = true;
+          ws.size = new_size;
+          self.needs_resize = true;
         }
       },
       WindowEvent::KeyboardInput{ device_id: _id, event, is_synthetic: synth } => {
         if synth { return; }
         
-        self.engine.inp_man.process_winit_key(event);
+        self.key_events.push((event, self.modifiers));
       },
-      WindowEvent::RedrawRequested => {
-        
-        let new_time = Instant::now();
-        let frame_time = new_time - self.current_time;
-        self.current_time = new_time;
-        
-        self.accumulator += frame_time;
-        
-        let runs_update = self.accumulator >= self.engine.fixed_time_step;
-        
-        while self.accumulator >= self.engine.fixed_time_step {
-          self.game_step(event_loop);
-          
-          self.accumulator -= self.engine.fixed_time_step;
-        }
-        
-        if runs_update {
-          self.game_state.draw(&mut self.engine);
-        }
-        
-        match self.draw() {
-          Ok(_) => {}
-          Err(SurfaceError::Lost) => { // If the swapchain is lost (e.g. driver update, monitor unplugged), recreate it
-            if let Some(ws) = &mut self.window_state {
-              ws.resize(ws.size, self.engine.db.height, self.engine.db.width);
-              self.engine.db.text_changed = true;
-            }
+      WindowEvent::ModifiersChanged(modifiers) => {
+        self.modifiers = modifiers.state();
+      },
+      WindowEvent::Ime(Ime::Commit(text)) => {
+        self.ime_commits.push(text);
+      },
+      WindowEvent::CursorMoved{ device_id: _id, position } => {
+        self.cursor_pixels = Some((position.x, position.y));
+      },
+      WindowEvent::CursorLeft{ device_id: _id } => {
+        self.cursor_pixels = None;
+      },
+      WindowEvent::MouseInput{ device_id: _id, state, button } => {
+        self.mouse_buttons.push((button, state));
+      },
+      WindowEvent::MouseWheel{ device_id: _id, delta, phase: _phase } => {
+        // winit's wheel goes up for positive values, ours goes down
+        let (x, y) = match delta {
+          MouseScrollDelta::LineDelta(x, y) => (x, -y),
+          MouseScrollDelta::PixelDelta(pixels) => {
+            let cell_size = self.window_state.as_ref().map_or(32.0, |ws| ws.metrics.line_height);
+            (pixels.x as f32 / cell_size, -pixels.y as f32 / cell_size)
           },
-          Err(SurfaceError::OutOfMemory) => event_loop.exit(), // The system is out of memory, we should quit
-          Err(e) => eprintln!("{:?}", e), // All other errors (Outdated, Timeout) should be resolved by the next frame
-        }        
-        // end of game logic
-        
-        // Queue a RedrawRequested event.
-        if let Some(ws) = &mut self.window_state {
-          ws.render(); // calls window.request_redraw()
-        }
-      }
+        };
+        self.wheel_lines.0 += x;
+        self.wheel_lines.1 += y;
+      },
       _ => (),
     }
   }
   
 }
+
+/// WindowBackend. Renders the DrawBuffer with wgpu into a winit window, pumping the event loop once per frame.
+pub struct WindowBackend {
+  event_loop: EventLoop<()>,
+  app: WindowApp,
+}
+
+impl WindowBackend {
+  pub fn new() -> Result<Self> {
+    Ok(Self {
+      event_loop: EventLoop::new()?,
+      app: WindowApp {
+        window_state: None,
+        
+        key_events: Vec::new(),
+        modifiers: ModifiersState::empty(),
+        ime_commits: Vec::new(),
+        ime_allowed: false,
+        mouse_buttons: Vec::new(),
+        cursor_pixels: None,
+        wheel_lines: (0.0, 0.0),
+        needs_resize: false,
+        grid_size: (0, 0),
+        quit: false,
+      },
+    })
+  }
+}
+
+impl Backend for WindowBackend {
+  
+  fn size(&self) -> (usize, usize) {
+    (32, 18)
+  }
+  
+  fn pump_input(&mut self, inp_man: &mut InputManager) -> Result<()> {
+    use winit::platform::pump_events::{EventLoopExtPumpEvents, PumpStatus};
+    
+    if let PumpStatus::Exit(_) = self.event_loop.pump_app_events(Some(Duration::ZERO), &mut self.app) {
+      self.app.quit = true;
+    }
+    
+    for (key_event, modifiers) in self.app.key_events.drain(..) {
+      inp_man.process_winit_key(key_event, modifiers);
+    }
+    // releasing a modifier alone sends no key event with the new state
+    inp_man.modifiers = translate_winit_modifiers(self.app.modifiers);
+    
+    for text in self.app.ime_commits.drain(..) {
+      inp_man.insert_text(&text);
+    }
+    
+    // the IME only pops up while the game wants text
+    if let Some(ws) = &self.app.window_state && self.app.ime_allowed != inp_man.is_text_input() {
+      self.app.ime_allowed = inp_man.is_text_input();
+      ws.window.set_ime_allowed(self.app.ime_allowed);
+    }
+    
+    for (button, state) in self.app.mouse_buttons.drain(..) {
+      inp_man.process_winit_mouse_button(button, state);
+    }
+    
+    inp_man.mouse_position = match (&self.app.window_state, self.app.cursor_pixels) {
+      (Some(ws), Some((x, y))) => ws.pixel_to_cell(x, y),
+      _ => None,
+    };
+    
+    // whole lines only, the rest is kept for the next frames
+    let (lines_x, lines_y) = (self.app.wheel_lines.0.trunc(), self.app.wheel_lines.1.trunc());
+    inp_man.scroll(lines_x as i32, lines_y as i32);
+    self.app.wheel_lines.0 -= lines_x;
+    self.app.wheel_lines.1 -= lines_y;
+    
+    Ok(())
+  }
+  
+  fn present(&mut self, db: &mut DrawBuffer, _offset: (i32, i32)) -> Result<()> {
+    let app = &mut self.app;
+    
+    let Some(ws) = &mut app.window_state else {
+      return Ok(());
+    };
+    
+    if app.needs_resize || app.grid_size != (db.width, db.height) {
+      ws.resize(ws.size, db.height, db.width);
+      db.text_changed = true;
+      
+      app.needs_resize = false;
+      app.grid_size = (db.width, db.height);
+    }
+    
+    match ws.draw(db) {
+      Ok(_) => {}
+      Err(SurfaceError::Lost) => { // If the swapchain is lost (e.g. driver update, monitor unplugged), recreate it
+        app.needs_resize = true;
+      },
+      Err(SurfaceError::OutOfMemory) => app.quit = true, // The system is out of memory, we should quit
+      Err(e) => eprintln!("{:?}", e), // All other errors (Outdated, Timeout) should be resolved by the next frame
+    }
+    
+    Ok(())
+  }
+  
+  fn should_quit(&self) -> bool {
+    self.app.quit
+  }
+  
+}
+
+/// WindowGame. Runs a GameState inside its own window.
+pub type WindowGame<GS> = Game<GS, WindowBackend>;
+
+impl<GS> Game<GS, WindowBackend>
+where GS: GameState {
+  pub fn new() -> Result<Self> {
+    Self::with_backend(WindowBackend::new()?)
+  }
+}
@@ -1,7 +1,43 @@
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum Cell {
   Void,
   Wall,
   Floor,
   Corridor,
 }
+
+impl Cell {
+  pub fn is_walkable(&self) -> bool {
+    *self == Cell::Floor || *self == Cell::Corridor
+  }
+}
+
+/// The dungeon's cells, row by row.
+#[derive(Clone)]
+pub struct Map {
+  pub dims: (usize, usize),
+  pub cells: Vec<Cell>,
+}
+
+impl Map {
+  /// A map of nothing but Void.
+  pub fn new(dims: (usize, usize)) -> Self {
+    Self {
+      dims,
+      cells: vec![Cell::Void; dims.0 * dims.1],
+    }
+  }
+
+  pub fn in_bounds(&self, position: (i32, i32)) -> bool {
+    position.0 >= 0 && position.1 >= 0 &&
+    position.0 < self.dims.0 as i32 && position.1 < self.dims.1 as i32
+  }
+
+  pub fn get_cell(&self, x: usize, y: usize) -> Cell {
+    self.cells[x + y * self.dims.0]
+  }
+
+  pub fn set_cell(&mut self, x: usize, y: usize, cell: Cell) {
+    self.cells[x + y * self.dims.0] = cell;
+  }
+}
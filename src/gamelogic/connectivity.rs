@@ -0,0 +1,295 @@
+use asciigame::{DijkstraMap, Neighbourhood};
+
+use crate::common_structs::{*};
+
+use std::collections::{HashSet, VecDeque};
+
+// The player only walks in four directions, so that's what connected means here.
+
+/// Walkable cells that can't be walked to from 'spawn', grouped into regions that are connected among themselves.
+pub fn unreachable_regions(map: &Map, spawn: (i32, i32)) -> Vec<Vec<(i32, i32)>> {
+  let reachable = reachable_from(map, spawn);
+  let (width, height) = map.dims;
+
+  let mut seen = vec![false; width * height];
+  let mut regions = vec![];
+
+  for y in 0..height {
+    for x in 0..width {
+      let index = x + y * width;
+      if seen[index] || reachable.get((x as i32, y as i32)).is_some() || !map.get_cell(x, y).is_walkable() { continue; }
+
+      // flood fill the whole region from its first cell
+      let mut region = vec![];
+      let mut to_look = VecDeque::from([(x as i32, y as i32)]);
+      seen[index] = true;
+
+      while let Some(current) = to_look.pop_front() {
+        region.push(current);
+
+        for next in Neighbourhood::Four.neighbours(current) {
+          if !map.in_bounds(next) || !map.get_cell(next.0 as usize, next.1 as usize).is_walkable() { continue; }
+
+          let next_index = next.0 as usize + next.1 as usize * width;
+          if !seen[next_index] {
+            seen[next_index] = true;
+            to_look.push_back(next);
+          }
+        }
+      }
+
+      regions.push(region);
+    }
+  }
+
+  regions
+}
+
+/// What 'connect_regions' did to a map.
+#[derive(Clone, Default, Debug)]
+pub struct Connections {
+  pub dug: Vec<usize>, // size of every region a corridor was dug to, in the order they were dug
+  pub cut_off: Vec<Vec<(i32, i32)>>, // regions no corridor could be dug to
+}
+
+impl Connections {
+  /// True when everything walkable can be walked to from the spawn.
+  pub fn is_connected(&self) -> bool {
+    self.cut_off.is_empty()
+  }
+}
+
+/// Digs a corridor from every unreachable region to the part of the map reachable from 'spawn',
+/// until the whole map can be walked. A spawn inside rock is dug out first.
+/// Regions that can't be dug to (walled in by the map border) are left as they are, see 'Connections::cut_off'.
+pub fn connect_regions(map: &mut Map, spawn: (i32, i32)) -> Connections {
+  let mut connections = Connections::default();
+  let mut cut_off = HashSet::new();
+
+  if map.in_bounds(spawn) && !map.get_cell(spawn.0 as usize, spawn.1 as usize).is_walkable() {
+    dig(map, &[spawn]);
+  }
+
+  loop {
+    let regions = unreachable_regions(map, spawn);
+    let Some(region) = regions.iter().find(|region| !cut_off.contains(&region[0])) else { return connections; };
+
+    let reachable = reachable_from(map, spawn);
+    let goals = all_cells(map).filter(|&cell| reachable.get(cell).is_some());
+
+    // digging through walls costs more than through the void, so corridors go around rooms rather than through them.
+    // The border is never dug, the map has to stay closed
+    let digging = DijkstraMap::new(map.dims, goals, Neighbourhood::Four, |_, to| {
+      if !map.in_bounds(to) { return None; }
+
+      match map.get_cell(to.0 as usize, to.1 as usize) {
+        Cell::Floor | Cell::Corridor => Some(1),
+        _ if on_border(map, to) => None,
+        Cell::Void => Some(2),
+        Cell::Wall => Some(5),
+      }
+    });
+
+    let Some(mut current) = region.iter().copied().filter(|&cell| digging.get(cell).is_some()).min_by_key(|&cell| digging.get(cell)) else {
+      // walled in by the border, nothing can reach it
+      cut_off.extend(region.iter().copied());
+      connections.cut_off.push(region.clone());
+      continue;
+    };
+
+    let mut corridor = vec![current];
+    while let Some(next) = digging.downhill(current) {
+      corridor.push(next);
+      current = next;
+    }
+
+    dig(map, &corridor);
+    connections.dug.push(region.len());
+  }
+}
+
+/// Turns the cells into corridor, and the void around them into walls.
+fn dig(map: &mut Map, cells: &[(i32, i32)]) {
+  for &(x, y) in cells {
+    if !map.get_cell(x as usize, y as usize).is_walkable() {
+      map.set_cell(x as usize, y as usize, Cell::Corridor);
+    }
+  }
+
+  for &(x, y) in cells {
+    let around = (-1..=1).flat_map(|dy| (-1..=1).map(move |dx| (x + dx, y + dy)));
+
+    for (x, y) in around {
+      if map.in_bounds((x, y)) && map.get_cell(x as usize, y as usize) == Cell::Void {
+        map.set_cell(x as usize, y as usize, Cell::Wall);
+      }
+    }
+  }
+}
+
+fn on_border(map: &Map, (x, y): (i32, i32)) -> bool {
+  x == 0 || y == 0 || x as usize == map.dims.0 - 1 || y as usize == map.dims.1 - 1
+}
+
+/// Distance of every cell reachable from 'spawn'. The spawn itself is always in it, walkable or not.
+fn reachable_from(map: &Map, spawn: (i32, i32)) -> DijkstraMap {
+  DijkstraMap::new(map.dims, [spawn], Neighbourhood::Four, |_, to| {
+    (map.in_bounds(to) && map.get_cell(to.0 as usize, to.1 as usize).is_walkable()).then_some(1)
+  })
+}
+
+fn all_cells(map: &Map) -> impl Iterator<Item = (i32, i32)> {
+  let (width, height) = map.dims;
+
+  (0..height as i32).flat_map(move |y| (0..width as i32).map(move |x| (x, y)))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::mapgen::{*};
+  use rand::{SeedableRng, rngs::StdRng};
+
+  // '#' is a wall, '.' floor, ',' corridor and ' ' the void
+  fn parse(rows: &[&str]) -> Map {
+    let mut map = Map::new((rows[0].len(), rows.len()));
+
+    for (y, row) in rows.iter().enumerate() {
+      for (x, c) in row.chars().enumerate() {
+        let cell = match c {
+          '#' => Cell::Wall,
+          '.' => Cell::Floor,
+          ',' => Cell::Corridor,
+          _ => Cell::Void,
+        };
+        map.set_cell(x, y, cell);
+      }
+    }
+
+    map
+  }
+
+  fn changed_cells(before: &Map, after: &Map) -> Vec<(i32, i32)> {
+    all_cells(before).filter(|&(x, y)| before.get_cell(x as usize, y as usize) != after.get_cell(x as usize, y as usize)).collect()
+  }
+
+  fn assert_walled_in(map: &Map, cells: &[(i32, i32)]) {
+    for &(x, y) in cells {
+      for next in Neighbourhood::Eight.neighbours((x, y)).filter(|&next| map.in_bounds(next)) {
+        assert_ne!(map.get_cell(next.0 as usize, next.1 as usize), Cell::Void, "{:?} is open to the void", (x, y));
+      }
+    }
+  }
+
+  fn connect_generated_maps(seeds: u64) {
+    for kind in [GeneratorKind::Rooms, GeneratorKind::Bsp, GeneratorKind::Caves, GeneratorKind::Walk] {
+      for seed in 0..seeds {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let GeneratedMap { mut map, spawns } = kind.generator().generate((100, 50), &mut rng);
+        let spawn = spawns[0];
+
+        assert!(connect_regions(&mut map, spawn).is_connected(), "{:?} seed {}: regions cut off", kind, seed);
+
+        assert!(map.get_cell(spawn.0 as usize, spawn.1 as usize).is_walkable(), "{:?} seed {}: spawn in rock", kind, seed);
+        assert!(unreachable_regions(&map, spawn).is_empty(), "{:?} seed {}: still unreachable", kind, seed);
+      }
+    }
+  }
+
+  #[test]
+  fn every_generated_map_gets_connected() {
+    connect_generated_maps(100);
+  }
+
+  // slow unoptimized, run with 'cargo test --release -- --ignored'
+  #[test]
+  #[ignore]
+  fn thousands_of_generated_maps_get_connected() {
+    connect_generated_maps(1000);
+  }
+
+  #[test]
+  fn two_rooms_get_a_corridor_through_the_wall() {
+    let mut map = parse(&[
+      "#########",
+      "#...#...#",
+      "#...#...#",
+      "#########",
+    ]);
+    let before = map.clone();
+
+    let regions = unreachable_regions(&map, (1, 1));
+    assert_eq!(regions.len(), 1);
+    let mut region = regions[0].clone();
+    region.sort();
+    assert_eq!(region, [(5, 1), (5, 2), (6, 1), (6, 2), (7, 1), (7, 2)]);
+
+    assert_eq!(connect_regions(&mut map, (1, 1)).dug, [6]);
+    assert!(unreachable_regions(&map, (1, 1)).is_empty());
+
+    // the shortest way is a single cell of the dividing wall
+    let dug = changed_cells(&before, &map);
+    assert_eq!(dug.len(), 1);
+    assert!(dug[0] == (4, 1) || dug[0] == (4, 2), "dug at {:?}", dug[0]);
+    assert_eq!(map.get_cell(dug[0].0 as usize, dug[0].1 as usize), Cell::Corridor);
+  }
+
+  #[test]
+  fn corridors_through_the_void_get_walls_and_stay_off_the_border() {
+    let mut map = parse(&[
+      "#####     ",
+      "#...#     ",
+      "#...#  ###",
+      "#####  #.#",
+      "       ###",
+    ]);
+    let before = map.clone();
+
+    assert_eq!(connect_regions(&mut map, (1, 1)).dug, [1]);
+    assert!(unreachable_regions(&map, (1, 1)).is_empty());
+
+    let changed = changed_cells(&before, &map);
+    let corridor: Vec<_> = changed.iter().copied().filter(|&(x, y)| map.get_cell(x as usize, y as usize) == Cell::Corridor).collect();
+    assert!(!corridor.is_empty());
+    assert_walled_in(&map, &corridor);
+
+    let (width, height) = map.dims;
+    for &(x, y) in &corridor {
+      assert!(x > 0 && y > 0 && x < width as i32 - 1 && y < height as i32 - 1, "dug the border at {:?}", (x, y));
+    }
+  }
+
+  #[test]
+  fn a_spawn_in_rock_is_dug_out() {
+    let mut map = parse(&[
+      "       ",
+      "       ",
+      "       ",
+      "  ...  ",
+      "       ",
+    ]);
+
+    assert_eq!(connect_regions(&mut map, (2, 1)).dug, [3]);
+    assert!(map.get_cell(2, 1).is_walkable());
+    assert!(unreachable_regions(&map, (2, 1)).is_empty());
+    assert_walled_in(&map, &[(2, 1)]);
+  }
+
+  #[test]
+  fn regions_walled_in_by_the_border_dont_stop_the_others() {
+    let mut map = parse(&[
+      ".#########",
+      "##...#...#",
+      "#....#...#",
+      "##########",
+    ]);
+
+    let connections = connect_regions(&mut map, (2, 1));
+
+    // the corner can only be reached through the border
+    assert!(!connections.is_connected());
+    assert_eq!(connections.cut_off, [vec![(0, 0)]]);
+    assert_eq!(connections.dug, [6]);
+    assert_eq!(unreachable_regions(&map, (2, 1)), [vec![(0, 0)]]);
+  }
+}
@@ -1,13 +1,18 @@
 use asciigame::{*};
 use crossterm::event::{KeyCode};
+use std::collections::HashSet;
 
 use crate::common_structs::{*};
+use crate::mapgen::{*};
+use crate::connectivity::{*};
 // use crate::gs_funcs::{*};
 
 pub struct Walker {
-  pub screen_dims: (usize, usize),
-  pub map: Vec<Cell>,
+  pub map: Map,
   pub map_seen: Vec<bool>,
+  pub visible: HashSet<(i32, i32)>, // the player's field of view
+  pub generator: GeneratorKind,
+  pub repairs: Connections, // corridors dug to the unreachable parts of the map
   
   pub player_pos: (i32, i32),
   pub player_char: Character,
@@ -15,172 +20,137 @@ pub struct Walker {
   pub should_run: bool,
 }
 
-impl GameState for Walker {
-  fn new(ctx: &mut Engine<Self>) -> Self {
-    use rand::Rng;
-  
-    ctx.set_framerate(10);
+impl Walker {
+  pub fn with_generator(ctx: &mut Engine<Self>, generator: GeneratorKind) -> Self {
+    ctx.set_framerate(30);
+    ctx.resize_policy = ResizePolicy::Crop;
     
     let (swidth, sheight) = (60, 30);
-    ctx.db.resize(swidth, sheight);
+    let (mwidth, mheight) = (100, 50); // the map is bigger than the screen, the camera scrolls over it
+    ctx.db.resize(swidth, sheight + 1); // the last row is the status line
+    ctx.db.name_layer("map", DrawBuffer::BASE_LAYER)
+      .name_layer("entities", 1)
+      .name_layer("hud", 2);
+    
+    let GeneratedMap { mut map, spawns } = generator.generator().generate((mwidth, mheight), ctx.rng());
+    // corridors can fail to dig and caves come in pieces, every floor cell should be walkable to
+    let repairs = connect_regions(&mut map, spawns[0]);
   
-    let mut walker = Walker{
-      screen_dims: (swidth, sheight),
-      map: vec![Cell::Void; swidth * sheight],
-      map_seen: vec![false; swidth * sheight],
+    let walker = Walker{
+      map,
+      map_seen: vec![false; mwidth * mheight],
+      visible: HashSet::new(),
+      generator,
+      repairs,
       
-      player_pos: (10, 10),
-      player_char: Character{ symbol: '@', ..Default::default() },
+      player_pos: spawns[0],
+      player_char: Character{ symbol: '@', transparency: Transparency::Background, ..Default::default() },
       
       should_run: true,
     };
     
-    let mut rooms: Vec::<(usize, usize, usize, usize)> = vec![]; // room = (pos_x, pos_y, size_x, size_y)
-    let num_rooms = 5;
-    
-    // generating map
-    let mut rng = rand::rng();
-    for room_index in 0..num_rooms {
-    
-      let max_room_size = 20;
-      let min_room_size = 5;
-      
-      let mut tries: i32 = 0;
-      let mut failed = true;
-      
-      while failed {
-        if tries > 100 {
-          println!("failed");
-          break;
-        }
-      
-        failed = false;
-        tries += 1;
-        
-        let size = [rng.random_range(min_room_size..max_room_size); 2];
-        let pos = (rng.random_range(0..walker.screen_dims.0 - size[0]), rng.random_range(0..walker.screen_dims.1 - size[1]) );
-        
-        for room in rooms.iter() {
-          let new_range_x = (pos.0, pos.0 + size[0]-1);
-          let new_range_y = (pos.1, pos.1 + size[1]-1);
-          
-          let old_range_x = (room.0, room.0 + room.2-1);
-          let old_range_y = (room.1, room.1 + room.3-1);
-          
-          if !((new_range_x.1 < old_range_x.0 ||
-               old_range_x.1 < new_range_x.0) ||
-               (new_range_y.1 < old_range_y.0 ||
-               old_range_y.1 < new_range_y.0) ) {
-            failed = true;
-            break;
-          }
-        }
-        
-        if failed { continue }
-        rooms.push( (pos.0, pos.1, size[0], size[1]) );
-        
-        if room_index == 0 { walker.player_pos = (pos.0 as i32 + 2, pos.1 as i32 + 2); }
-      }
-      
-      
-      println!("{} tries", tries);
-    }
-    
-    
-    for room in &rooms {
-      for x in 0..room.2 as usize {
-        walker.map[room.0 + x + room.1 as usize * walker.screen_dims.0] = Cell::Wall;
-        walker.map[room.0 + x + (room.1 + room.3-1) as usize * walker.screen_dims.0] = Cell::Wall;
-      }
-      
-      for y in 0..room.3 as usize {
-        walker.map[room.0 + (room.1 + y) as usize * walker.screen_dims.0] = Cell::Wall;
-        walker.map[room.0 + room.2-1 + (room.1 + y) as usize * walker.screen_dims.0] = Cell::Wall;
-      }
-      
-      for y in 1..room.3-1 as usize {
-        for x in 1..room.2-1 as usize {
-          walker.map[room.0 + x + (room.1 + y) * walker.screen_dims.0] = Cell::Floor;
-        }
-      }
-    
-    }
-        
-    for iteration in 0..num_rooms {
-      let mut tries = 0;
-      
-      while tries < 20 {
-        let room1 = &rooms[iteration];
-        let room2 = &rooms[(iteration+1) % num_rooms];
-        
-        let possible_doors1 = [(room1.0, room1.1 + room1.3/2),
-                      (room1.0 + room1.2-1, room1.1 + room1.3/2),
-                      (room1.0 + room1.2/2, room1.1),
-                      (room1.0 + room1.2/2, room1.1 + room1.3-1)];
-        let possible_doors2 = [(room2.0, room2.1 + room2.3/2),
-                      (room2.0 + room2.2-1, room2.1 + room2.3/2),
-                      (room2.0 + room2.2/2, room2.1),
-                      (room2.0 + room2.2/2, room2.1 + room2.3-1)];
-        
-        let door1 = possible_doors1[rng.random_range(0..4)];
-        let door2 = possible_doors2[rng.random_range(0..4)];
-        
-        let corridor = walker.bfs_to_pos(&mut rng, door1, door2);
-        
-        if let Some(corridor) = corridor {
-          // println!("{:?}", corridor);
-        
-          for cell in corridor {
-            walker.map[cell.0 + cell.1 * walker.screen_dims.0] = Cell::Corridor;
-          }
-        } else {
-          tries += 1;
-          continue;
-        }
-        break;
-      }
-    }
+    ctx.camera = Camera::new(Rect::new(0, 0, swidth, sheight))
+      .deadzone(swidth / 3, sheight / 3)
+      .bounds(Rect::new(0, 0, mwidth, mheight));
+    ctx.camera.center_on(walker.player_pos);
     
     // binding keys
-    ctx.bind(KeyCode::Esc, KeyState::Pressed, |gs| { gs.should_run = false; } );
+    ctx.inp_man.actions
+      .rebind("quit", [KeyCode::Esc])
+      .rebind("move_up", [KeyCode::Char('w'), KeyCode::Up, KeyCode::Char('k')])
+      .bind("move_up", KeyChord::on_keypad(KeyCode::Char('8')))
+      .rebind("move_down", [KeyCode::Char('s'), KeyCode::Down, KeyCode::Char('j')])
+      .bind("move_down", KeyChord::on_keypad(KeyCode::Char('2')))
+      .rebind("move_right", [KeyCode::Char('d'), KeyCode::Right, KeyCode::Char('l')])
+      .bind("move_right", KeyChord::on_keypad(KeyCode::Char('6')))
+      .rebind("move_left", [KeyCode::Char('a'), KeyCode::Left, KeyCode::Char('h')])
+      .bind("move_left", KeyChord::on_keypad(KeyCode::Char('4')));
+    
+    ctx.bind_action("quit", KeyState::Pressed, |gs, _ctx| { gs.should_run = false; } );
     
-    ctx.bind(KeyCode::Char('w'), KeyState::Down, move |gs| { if gs.is_position_walkable((gs.player_pos.0, gs.player_pos.1 - 1)) { gs.player_pos.1 -= 1; } } );
-    ctx.bind(KeyCode::Char('s'), KeyState::Down, move |gs| { if gs.is_position_walkable((gs.player_pos.0, gs.player_pos.1 + 1)) { gs.player_pos.1 += 1; } } );
-    ctx.bind(KeyCode::Char('d'), KeyState::Down, move |gs| { if gs.is_position_walkable((gs.player_pos.0 + 1, gs.player_pos.1)) { gs.player_pos.0 += 1; } } );
-    ctx.bind(KeyCode::Char('a'), KeyState::Down, move |gs| { if gs.is_position_walkable((gs.player_pos.0 - 1, gs.player_pos.1)) { gs.player_pos.0 -= 1; } } );
+    ctx.bind_action_repeating("move_up", KeyRepeat::default(), move |gs, _ctx| { if gs.is_position_walkable((gs.player_pos.0, gs.player_pos.1 - 1)) { gs.player_pos.1 -= 1; } } );
+    ctx.bind_action_repeating("move_down", KeyRepeat::default(), move |gs, _ctx| { if gs.is_position_walkable((gs.player_pos.0, gs.player_pos.1 + 1)) { gs.player_pos.1 += 1; } } );
+    ctx.bind_action_repeating("move_right", KeyRepeat::default(), move |gs, _ctx| { if gs.is_position_walkable((gs.player_pos.0 + 1, gs.player_pos.1)) { gs.player_pos.0 += 1; } } );
+    ctx.bind_action_repeating("move_left", KeyRepeat::default(), move |gs, _ctx| { if gs.is_position_walkable((gs.player_pos.0 - 1, gs.player_pos.1)) { gs.player_pos.0 -= 1; } } );
     
     walker
   }
+}
+
+impl GameState for Walker {
+  fn new(ctx: &mut Engine<Self>) -> Self {
+    Self::with_generator(ctx, GeneratorKind::default())
+  }
   
   fn update(&mut self, ctx: &mut Engine<Walker>) {
     
+    ctx.camera.follow(self.player_pos);
+    
+    let (focus_x, focus_y) = ctx.camera.world_to_screen(self.player_pos);
+    ctx.focus = Some((focus_x as usize, focus_y as usize));
+    
+    self.visible = field_of_view(self.player_pos, 10, |x, y| self.blocks_sight(x, y));
+    for &(x, y) in &self.visible {
+      self.map_seen[y as usize * self.map.dims.0 + x as usize] = true;
+    }
+    
     if ctx.frame_counter > 20 { /* self.should_run = false; */ }
     
   }
   
   fn draw(&mut self, ctx: &mut Engine<Walker>) {
 
-    let (width, height) = ctx.db.get_size_usize();
+    let (width, height) = self.map.dims;
+    let camera = ctx.camera;
+    let on_screen = camera.visible();
     
-    for y in 0..=height - 1 {
-      for x in 0..=width - 1 {
+    ctx.db.set_layer_named("map");
+    ctx.db.view(camera.viewport).fill(Default::default());
+    let mut map_view = camera.view(&mut ctx.db);
+    
+    // only the part of the map under the camera gets drawn
+    let on_screen = on_screen.intersect(&Rect::new(0, 0, width, height));
+    for y in on_screen.y as usize..on_screen.y as usize + on_screen.height {
+      for x in on_screen.x as usize..on_screen.x as usize + on_screen.width {
         let cell_char;
-        if self.has_lineofsight( (self.player_pos.0 as usize, self.player_pos.1 as usize), (x, y), 10, false) {
-          cell_char = self.get_cell_char(self.map[y * width + x]);
-          self.map_seen[y * width + x] = true;
+        if self.visible.contains(&(x as i32, y as i32)) {
+          cell_char = self.get_cell_char(self.map.get_cell(x, y));
         } else {
           if self.map_seen[y * width + x] {
-            cell_char = self.get_cell_char(self.map[y * width + x]).dim_background_safe(20).dim_color_safe(30);
+            cell_char = self.get_cell_char(self.map.get_cell(x, y)).dim_background_safe(20).dim_color_safe(30);
           } else {
             cell_char = Default::default();
           }
         };
         
-        ctx.db.set_char(x, y, cell_char);
+        map_view.put_char(x as i32, y as i32, cell_char);
       }
     }
     
-    ctx.db.set_char(self.player_pos.0 as usize, self.player_pos.1 as usize, self.player_char);
+    ctx.db.set_layer_named("entities").clear();
+    camera.view(&mut ctx.db).put_char(self.player_pos.0, self.player_pos.1, self.player_char);
+    
+    // status line
+    let mut status = format!("seed: {}  map: {}", ctx.seed(), self.generator.name());
+    if !self.repairs.dug.is_empty() {
+      let sizes: Vec<String> = self.repairs.dug.iter().map(|size| size.to_string()).collect();
+      status.push_str(&format!("  dug: {} ({})", self.repairs.dug.len(), sizes.join(", ")));
+    }
+    if !self.repairs.is_connected() {
+      status.push_str(&format!("  cut off: {}", self.repairs.cut_off.len()));
+    }
+    if let Some((x, y)) = ctx.mouse_world() {
+      status.push_str(&format!("  cursor: {}, {}", x, y));
+    }
+    if ctx.inp_man.key_release_mode() == KeyReleaseMode::Synthesized {
+      status.push_str("  (guessed key releases)");
+    }
+    ctx.db.set_layer_named("hud");
+    
+    let status_row = Rect::new(0, camera.viewport.height as i32, camera.viewport.width, 1);
+    let mut status_view = ctx.db.view(status_row);
+    status_view.fill(Default::default());
+    status_view.print_aligned(0, 0, status_row.width, &status, &TextStyle::new(Color{r: 100, g: 100, b: 100}, Color{r: 0, g: 0, b: 0}));
     
   }
   
@@ -189,3 +159,54 @@ impl GameState for Walker {
   }
   
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn walker_game(seed: u64) -> HeadlessGame<Walker> {
+    let config = EngineConfig { seed: Some(seed), ..Default::default() };
+
+    Game::with_config(HeadlessBackend::new((60, 31)), config).unwrap()
+  }
+
+  fn player_on_screen(game: &mut HeadlessGame<Walker>) -> (usize, usize) {
+    let player_pos = game.game_state().player_pos;
+    let (x, y) = game.engine().camera.world_to_screen(player_pos);
+
+    (x as usize, y as usize)
+  }
+
+  #[test]
+  fn walker_moves_and_draws_the_player() {
+    let mut game = walker_game(7);
+    assert!(game.step().unwrap());
+
+    let screen_pos = player_on_screen(&mut game);
+    assert_eq!(game.engine().db[screen_pos].symbol, '@');
+
+    let status: String = (0..60).map(|x| game.engine().db[(x, 30)].symbol).collect();
+    assert!(status.starts_with("seed: 7 "), "status line: {}", status);
+
+    // the first walkable direction next to the player
+    let start = game.game_state().player_pos;
+    let moves = [(KeyCode::Char('d'), (1, 0)), (KeyCode::Char('a'), (-1, 0)), (KeyCode::Char('s'), (0, 1)), (KeyCode::Char('w'), (0, -1))];
+    let (key, (dx, dy)) = moves.into_iter()
+      .find(|(_, (dx, dy))| game.game_state().is_position_walkable((start.0 + dx, start.1 + dy)))
+      .expect("the spawn is walled in");
+
+    game.press_key(key);
+    game.step().unwrap();
+    game.release_key(key);
+    game.step().unwrap();
+
+    assert_eq!(game.game_state().player_pos, (start.0 + dx, start.1 + dy));
+    let moved_pos = player_on_screen(&mut game);
+    assert_eq!(game.engine().db[moved_pos].symbol, '@');
+    assert_ne!(game.engine().db[screen_pos].symbol, '@');
+
+    game.press_key(KeyCode::Esc);
+    game.step().unwrap();
+    assert!(!game.step().unwrap(), "Esc stops the game");
+  }
+}
@@ -7,19 +7,8 @@ use std::collections::{HashMap, VecDeque};
 
 impl Walker {
   pub fn is_position_walkable(&mut self, position: (i32, i32)) -> bool {
-    if position.0 < 0 || position.1 < 0 {
-      false
-    } else if position.0 >= self.screen_dims.0 as i32 || position.1 >= self.screen_dims.1 as i32 {
-      false
-    } else {
-      let pos = &self.map[position.1 as usize * self.screen_dims.0 + position.0 as usize];
-      
-      *pos == Cell::Floor || *pos == Cell::Corridor
-    }
-  }
-  
-  pub fn get_cell(&self, x: usize, y: usize) -> Cell {
-    self.map[x + y * self.screen_dims.0]
+    self.map.in_bounds(position) &&
+    self.map.get_cell(position.0 as usize, position.1 as usize).is_walkable()
   }
   
   pub fn get_cell_char(&mut self, cell_type: Cell) -> Character {
@@ -28,21 +17,38 @@ impl Walker {
       Cell::Wall => Character{
         symbol: '#',
         color: Color{r: 100, g: 100, b: 100},
-        color_back: Color{r: 20, g: 20, b: 20}
+        color_back: Color{r: 20, g: 20, b: 20},
+        ..Default::default()
       },
       Cell::Floor => Character{
         symbol: ',',
         color: Color{r: 50, g: 50, b: 50},
-        color_back: Color{r: 1, g: 1, b: 1}
+        color_back: Color{r: 1, g: 1, b: 1},
+        ..Default::default()
       },
       Cell::Corridor => Character{
         symbol: '.',
         color: Color{r: 50, g: 50, b: 50},
-        color_back: Color{r: 1, g: 1, b: 1}
+        color_back: Color{r: 1, g: 1, b: 1},
+        ..Default::default()
       },
     }
   }
   
+  /// Walls and the void around the rooms block the view, so does everything outside of the map.
+  pub fn blocks_sight(&self, x: i32, y: i32) -> bool {
+    if !self.map.in_bounds((x, y)) {
+      return true;
+    }
+
+    let cell = self.map.get_cell(x as usize, y as usize);
+    cell == Cell::Wall || cell == Cell::Void
+  }
+  
+}
+
+impl Map {
+  #[allow(dead_code)]
   pub fn dfs_to_pos<T: rand::Rng>(&mut self, rng: &mut T, start: (usize, usize), end: (usize, usize)) -> Option<Vec<(usize, usize)>> {
     self.dfs_or_bfs(rng, start, end, true)
   }
@@ -58,7 +64,7 @@ impl Walker {
     
     let mut came_from = HashMap::<(usize, usize), (usize, usize)>::new();
     let mut to_look = VecDeque::new();
-    let mut visited = vec![false; self.screen_dims.0 * self.screen_dims.1];
+    let mut visited = vec![false; self.dims.0 * self.dims.1];
     to_look.push_back( start );
     
     while let Some(current) = if is_dfs { to_look.pop_back() } else { to_look.pop_front() } {
@@ -80,14 +86,14 @@ impl Walker {
         return Some(path);
       }
       
-      if current.0 >= self.screen_dims.0 ||
-         current.1 >= self.screen_dims.1 { continue; }
+      if current.0 >= self.dims.0 ||
+         current.1 >= self.dims.1 { continue; }
       
       if self.get_cell(current.0, current.1) != Cell::Void &&
          self.get_cell(current.0, current.1) != Cell::Corridor &&
          current != start { continue; }
       
-      let visit_bool = &mut visited[current.0 + current.1 * self.screen_dims.0];
+      let visit_bool = &mut visited[current.0 + current.1 * self.dims.0];
       if *visit_bool {
         continue;
       } else {
@@ -103,11 +109,11 @@ impl Walker {
         let pos_y = current.1 as i32 + dir.1;
         
         if pos_x >= 0 && pos_y >= 0 &&
-           pos_x < self.screen_dims.0 as i32 && pos_y < self.screen_dims.0 as i32 {
+           pos_x < self.dims.0 as i32 && pos_y < self.dims.1 as i32 {
           let new_pos = (pos_x as usize, pos_y as usize);
           
-          if !came_from.contains_key(&new_pos) {
-            came_from.insert(new_pos, current);
+          if let std::collections::hash_map::Entry::Vacant(entry) = came_from.entry(new_pos) {
+            entry.insert(current);
             to_look.push_back( new_pos );
           }
         }
@@ -118,71 +124,4 @@ impl Walker {
     None
   }
   
-  pub fn has_lineofsight(
-      &self,
-      uorigin: (usize, usize),
-      utarget: (usize, usize),
-      max_dist: usize,
-      check_corners: bool,
-  ) -> bool {
-    let origin = (uorigin.0 as i32, uorigin.1 as i32);
-    let target = (utarget.0 as i32, utarget.1 as i32);
-    
-    let dx = target.0 - origin.0;
-    let dy = target.1 - origin.1;
-    
-    let dmax = dx.abs().max(dy.abs());
-    let dmin = dx.abs().min(dy.abs());
-    
-    let sx = if dx > 0 { 1 } else { -1 };
-    let sy = if dy > 0 { 1 } else { -1 };
-    
-    let (mut oldx, mut oldy) = (0, 0);
-    
-    let rounding_error = dmax / 2;
-    
-    for i in 1..=dmax {
-    
-      let (x, y);
-      
-      if dx.abs() > dy.abs() {
-        x = i * sx;
-        y = (i * sy * dmin + rounding_error*sy) / dmax;
-      } else {
-        y = i * sy;
-        x = (i * sx * dmin + rounding_error*sx) / dmax;
-      }
-      
-      let physical_x = x + origin.0;
-      let physical_y = y + origin.1;
-      
-      if physical_x < 0 || physical_y < 0 {
-        return false;
-      }
-      
-      if (x,y) != (oldx, oldy) && check_corners && (x,y) != target {
-        
-        return_if!(
-          self.get_cell((oldx + origin.0) as usize, physical_y as usize) == Cell::Wall ||
-          self.get_cell((oldx + origin.0) as usize, physical_y as usize) == Cell::Void,
-          false);
-        return_if!(
-          self.get_cell(physical_x as usize, (oldy + origin.1) as usize) == Cell::Wall ||
-          self.get_cell(physical_x as usize, (oldy + origin.1) as usize) == Cell::Void,
-          false);
-        
-      }
-      
-      (oldx, oldy) = (x, y);
-            
-      return_if!(
-        (self.get_cell(physical_x as usize, physical_y as usize) == Cell::Wall ||
-        self.get_cell(physical_x as usize, physical_y as usize) == Cell::Void) &&
-        (physical_x, physical_y) != target,
-        false);
-    }
-    
-    true
-  }
-  
 }
@@ -0,0 +1,464 @@
+use crate::common_structs::{*};
+
+use rand::{Rng, rngs::StdRng};
+use std::str::FromStr;
+
+/// A freshly generated map and the places things can start on. The first spawn point is the player's.
+pub struct GeneratedMap {
+  pub map: Map,
+  pub spawns: Vec<(i32, i32)>,
+}
+
+/// Builds a map of the given size. All of its randomness comes from 'rng', so the same seed gives the same map.
+/// Maps smaller than 3x3 have no room for a floor cell inside walls, they come out empty.
+pub trait MapGenerator {
+  fn generate(&self, dims: (usize, usize), rng: &mut StdRng) -> GeneratedMap;
+}
+
+/// The generators the game can be started with (see the '--generator' option).
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum GeneratorKind {
+  #[default]
+  Rooms,
+  Bsp,
+  Caves,
+  Walk,
+}
+
+impl GeneratorKind {
+  pub fn name(&self) -> &'static str {
+    match self {
+      GeneratorKind::Rooms => "rooms",
+      GeneratorKind::Bsp => "bsp",
+      GeneratorKind::Caves => "caves",
+      GeneratorKind::Walk => "walk",
+    }
+  }
+
+  pub fn generator(&self) -> Box<dyn MapGenerator> {
+    match self {
+      GeneratorKind::Rooms => Box::new(RoomsAndCorridors::default()),
+      GeneratorKind::Bsp => Box::new(Bsp::default()),
+      GeneratorKind::Caves => Box::new(Caves::default()),
+      GeneratorKind::Walk => Box::new(DrunkardsWalk::default()),
+    }
+  }
+}
+
+impl FromStr for GeneratorKind {
+  type Err = anyhow::Error;
+
+  fn from_str(name: &str) -> anyhow::Result<Self> {
+    [GeneratorKind::Rooms, GeneratorKind::Bsp, GeneratorKind::Caves, GeneratorKind::Walk].into_iter()
+      .find(|kind| kind.name() == name)
+      .ok_or_else(|| anyhow::anyhow!("unknown generator '{}', expected rooms, bsp, caves or walk", name))
+  }
+}
+
+/// Rejection-sampled rooms, each joined to the next one by a corridor dug through the void.
+pub struct RoomsAndCorridors {
+  pub rooms: usize,
+  pub min_room_size: usize,
+  pub max_room_size: usize,
+}
+
+impl Default for RoomsAndCorridors {
+  fn default() -> Self {
+    Self {
+      rooms: 9,
+      min_room_size: 5,
+      max_room_size: 20,
+    }
+  }
+}
+
+impl MapGenerator for RoomsAndCorridors {
+  fn generate(&self, dims: (usize, usize), rng: &mut StdRng) -> GeneratedMap {
+    if let Some(empty) = too_small(dims) { return empty; }
+
+    let mut map = Map::new(dims);
+    let mut spawns = vec![];
+
+    // rooms are clamped to the map, and need 3x3 for a floor cell inside their walls
+    let largest = self.max_room_size.saturating_sub(1).min(dims.0).min(dims.1).max(3);
+    let smallest = self.min_room_size.clamp(3, largest);
+
+    let mut rooms: Vec::<(usize, usize, usize, usize)> = vec![]; // room = (pos_x, pos_y, size_x, size_y)
+
+    for _ in 0..self.rooms {
+      // a room that doesn't fit after 100 tries is left out
+      for _ in 0..100 {
+        let size = [rng.random_range(smallest..=largest); 2];
+        let pos = (rng.random_range(0..=dims.0 - size[0]), rng.random_range(0..=dims.1 - size[1]) );
+
+        let overlaps = rooms.iter().any(|room| {
+          let new_range_x = (pos.0, pos.0 + size[0]-1);
+          let new_range_y = (pos.1, pos.1 + size[1]-1);
+
+          let old_range_x = (room.0, room.0 + room.2-1);
+          let old_range_y = (room.1, room.1 + room.3-1);
+
+          !((new_range_x.1 < old_range_x.0 ||
+             old_range_x.1 < new_range_x.0) ||
+            (new_range_y.1 < old_range_y.0 ||
+             old_range_y.1 < new_range_y.0) )
+        });
+
+        if overlaps { continue }
+        rooms.push( (pos.0, pos.1, size[0], size[1]) );
+
+        if spawns.is_empty() {
+          let inset = (size[0] / 2).min(2);
+          spawns.push(((pos.0 + inset) as i32, (pos.1 + inset) as i32));
+        } else {
+          spawns.push(((pos.0 + size[0]/2) as i32, (pos.1 + size[1]/2) as i32));
+        }
+        break;
+      }
+    }
+
+    for room in &rooms {
+      for x in 0..room.2 {
+        map.set_cell(room.0 + x, room.1, Cell::Wall);
+        map.set_cell(room.0 + x, room.1 + room.3-1, Cell::Wall);
+      }
+
+      for y in 0..room.3 {
+        map.set_cell(room.0, room.1 + y, Cell::Wall);
+        map.set_cell(room.0 + room.2-1, room.1 + y, Cell::Wall);
+      }
+
+      for y in 1..room.3-1 {
+        for x in 1..room.2-1 {
+          map.set_cell(room.0 + x, room.1 + y, Cell::Floor);
+        }
+      }
+    }
+
+    for iteration in 0..rooms.len() {
+      let room1 = &rooms[iteration];
+      let room2 = &rooms[(iteration+1) % rooms.len()];
+
+      let possible_doors1 = [(room1.0, room1.1 + room1.3/2),
+                    (room1.0 + room1.2-1, room1.1 + room1.3/2),
+                    (room1.0 + room1.2/2, room1.1),
+                    (room1.0 + room1.2/2, room1.1 + room1.3-1)];
+      let possible_doors2 = [(room2.0, room2.1 + room2.3/2),
+                    (room2.0 + room2.2-1, room2.1 + room2.3/2),
+                    (room2.0 + room2.2/2, room2.1),
+                    (room2.0 + room2.2/2, room2.1 + room2.3-1)];
+
+      for _ in 0..20 {
+        let door1 = possible_doors1[rng.random_range(0..4)];
+        let door2 = possible_doors2[rng.random_range(0..4)];
+
+        if let Some(corridor) = map.bfs_to_pos(rng, door1, door2) {
+          for cell in corridor {
+            map.set_cell(cell.0, cell.1, Cell::Corridor);
+          }
+          break;
+        }
+      }
+    }
+
+    GeneratedMap { map, spawns }
+  }
+}
+
+/// Binary space partitioning: the map is split in two over and over, every leaf gets a room,
+/// and the two halves of every split are joined by a corridor, so everything is connected.
+pub struct Bsp {
+  pub min_leaf_size: usize, // a part isn't split any further when both halves would be smaller than this
+  pub min_room_size: usize, // inside size, without walls
+}
+
+impl Default for Bsp {
+  fn default() -> Self {
+    Self {
+      min_leaf_size: 12,
+      min_room_size: 4,
+    }
+  }
+}
+
+impl Bsp {
+  /// Fills a part of the map and returns a cell of one of its rooms, for the corridor to the other half.
+  fn split(&self, map: &mut Map, rng: &mut StdRng, leaf: (usize, usize, usize, usize), spawns: &mut Vec<(i32, i32)>) -> (usize, usize) {
+    let (x, y, width, height) = leaf;
+    let min_leaf_size = self.min_leaf_size.max(3);
+    let can_split_x = width >= 2 * min_leaf_size;
+    let can_split_y = height >= 2 * min_leaf_size;
+
+    let split_x = match (can_split_x, can_split_y) {
+      (false, false) => return self.room(map, rng, leaf, spawns),
+      (true, true) => if width == height { rng.random() } else { width > height },
+      (split_x, _) => split_x,
+    };
+
+    let (first, second) = if split_x {
+      let at = rng.random_range(min_leaf_size..=width - min_leaf_size);
+      ((x, y, at, height), (x + at, y, width - at, height))
+    } else {
+      let at = rng.random_range(min_leaf_size..=height - min_leaf_size);
+      ((x, y, width, at), (x, y + at, width, height - at))
+    };
+
+    let from = self.split(map, rng, first, spawns);
+    let to = self.split(map, rng, second, spawns);
+    dig_corridor(map, rng, from, to);
+
+    if rng.random() { from } else { to }
+  }
+
+  fn room(&self, map: &mut Map, rng: &mut StdRng, leaf: (usize, usize, usize, usize), spawns: &mut Vec<(i32, i32)>) -> (usize, usize) {
+    let (x, y, width, height) = leaf;
+
+    // a cell of margin on each side, for the walls. Leaves too small for 'min_room_size' get smaller rooms
+    let (max_width, max_height) = (width - 2, height - 2);
+    let room_width = rng.random_range(self.min_room_size.clamp(1, max_width)..=max_width);
+    let room_height = rng.random_range(self.min_room_size.clamp(1, max_height)..=max_height);
+    let room_x = rng.random_range(x + 1..=x + width - 1 - room_width);
+    let room_y = rng.random_range(y + 1..=y + height - 1 - room_height);
+
+    for cell_y in room_y..room_y + room_height {
+      for cell_x in room_x..room_x + room_width {
+        map.set_cell(cell_x, cell_y, Cell::Floor);
+      }
+    }
+
+    let center = (room_x + room_width / 2, room_y + room_height / 2);
+    spawns.push((center.0 as i32, center.1 as i32));
+
+    center
+  }
+}
+
+impl MapGenerator for Bsp {
+  fn generate(&self, dims: (usize, usize), rng: &mut StdRng) -> GeneratedMap {
+    if let Some(empty) = too_small(dims) { return empty; }
+
+    let mut map = Map::new(dims);
+    let mut spawns = vec![];
+
+    self.split(&mut map, rng, (0, 0, dims.0, dims.1), &mut spawns);
+    outline_walls(&mut map);
+
+    GeneratedMap { map, spawns }
+  }
+}
+
+/// Cellular automaton caves: random noise smoothed into round caverns.
+/// Nothing joins the caverns, so some of them may be cut off from the rest.
+pub struct Caves {
+  pub fill: f64, // chance of a cell starting out as rock
+  pub iterations: usize,
+  pub spawns: usize,
+}
+
+impl Default for Caves {
+  fn default() -> Self {
+    Self {
+      fill: 0.45,
+      iterations: 5,
+      spawns: 8,
+    }
+  }
+}
+
+impl MapGenerator for Caves {
+  fn generate(&self, dims: (usize, usize), rng: &mut StdRng) -> GeneratedMap {
+    if let Some(empty) = too_small(dims) { return empty; }
+
+    let (width, height) = dims;
+    let is_border = |x: usize, y: usize| x == 0 || y == 0 || x == width - 1 || y == height - 1;
+
+    let mut rock: Vec<bool> = (0..width * height)
+      .map(|index| is_border(index % width, index / width) || rng.random_bool(self.fill))
+      .collect();
+
+    // a cell becomes rock when most of the 3x3 block around it is rock, the map's outside counts as rock
+    for _ in 0..self.iterations {
+      rock = (0..width * height).map(|index| {
+        let (x, y) = ((index % width) as i32, (index / width) as i32);
+
+        let rock_around = (-1..=1).flat_map(|dy| (-1..=1).map(move |dx| (x + dx, y + dy)))
+          .filter(|&(x, y)| x < 0 || y < 0 || x >= width as i32 || y >= height as i32 || rock[y as usize * width + x as usize])
+          .count();
+
+        is_border(x as usize, y as usize) || rock_around >= 5
+      }).collect();
+    }
+
+    let mut map = Map::new(dims);
+    for (index, &rock) in rock.iter().enumerate() {
+      if !rock {
+        map.cells[index] = Cell::Floor;
+      }
+    }
+    outline_walls(&mut map);
+
+    // the player starts as close to the middle as it gets. If it all turned to rock, the middle is dug out
+    let center = ((width / 2) as i32, (height / 2) as i32);
+    let nearest = floor_cells(&map).into_iter()
+      .min_by_key(|&(x, y)| (x - center.0).pow(2) + (y - center.1).pow(2));
+
+    let player = match nearest {
+      Some(cell) => cell,
+      None => {
+        map.set_cell(center.0 as usize, center.1 as usize, Cell::Floor);
+        outline_walls(&mut map);
+        center
+      },
+    };
+
+    let mut spawns = vec![player];
+    spawns.extend(random_floor_cells(&map, rng, self.spawns));
+
+    GeneratedMap { map, spawns }
+  }
+}
+
+/// A drunkard stumbles around from the middle of the map, digging out every cell it steps on.
+pub struct DrunkardsWalk {
+  pub coverage: f64, // part of the map that gets dug out
+  pub spawns: usize,
+}
+
+impl Default for DrunkardsWalk {
+  fn default() -> Self {
+    Self {
+      coverage: 0.35,
+      spawns: 8,
+    }
+  }
+}
+
+impl MapGenerator for DrunkardsWalk {
+  fn generate(&self, dims: (usize, usize), rng: &mut StdRng) -> GeneratedMap {
+    if let Some(empty) = too_small(dims) { return empty; }
+
+    let (width, height) = dims;
+    let mut map = Map::new(dims);
+
+    let start = (width / 2, height / 2);
+    let mut position = start;
+
+    // the outermost ring stays rock, for the walls
+    let goal = ((self.coverage * (width - 2) as f64 * (height - 2) as f64) as usize).max(1);
+    let mut dug = 0;
+    let mut steps = 0;
+
+    while dug < goal && steps < 100 * width * height {
+      if map.get_cell(position.0, position.1) != Cell::Floor {
+        map.set_cell(position.0, position.1, Cell::Floor);
+        dug += 1;
+      }
+
+      let (dx, dy) = [(0, -1), (1, 0), (0, 1), (-1, 0)][rng.random_range(0..4)];
+      position = (
+        position.0.saturating_add_signed(dx).clamp(1, width - 2),
+        position.1.saturating_add_signed(dy).clamp(1, height - 2),
+      );
+      steps += 1;
+    }
+    outline_walls(&mut map);
+
+    let mut spawns = vec![(start.0 as i32, start.1 as i32)];
+    spawns.extend(random_floor_cells(&map, rng, self.spawns));
+
+    GeneratedMap { map, spawns }
+  }
+}
+
+/// The empty map 'generate' gives when there's no room for walls around a floor cell.
+fn too_small(dims: (usize, usize)) -> Option<GeneratedMap> {
+  if dims.0 >= 3 && dims.1 >= 3 { return None; }
+
+  Some(GeneratedMap { map: Map::new(dims), spawns: vec![((dims.0 / 2) as i32, (dims.1 / 2) as i32)] })
+}
+
+/// Digs an L-shaped corridor through the void. Rooms it runs through are left as they are.
+fn dig_corridor(map: &mut Map, rng: &mut StdRng, from: (usize, usize), to: (usize, usize)) {
+  let corner = if rng.random() { (to.0, from.1) } else { (from.0, to.1) };
+
+  for (start, end) in [(from, corner), (corner, to)] {
+    let (x_range, y_range) = (start.0.min(end.0)..=start.0.max(end.0), start.1.min(end.1)..=start.1.max(end.1));
+
+    for y in y_range {
+      for x in x_range.clone() {
+        if map.get_cell(x, y) == Cell::Void {
+          map.set_cell(x, y, Cell::Corridor);
+        }
+      }
+    }
+  }
+}
+
+/// Turns the void around everything walkable into walls, so the dug out parts look like rooms.
+fn outline_walls(map: &mut Map) {
+  let (width, height) = map.dims;
+
+  for y in 0..height {
+    for x in 0..width {
+      if map.get_cell(x, y) != Cell::Void { continue; }
+
+      let next_to_floor = (-1..=1).flat_map(|dy| (-1..=1).map(move |dx| (x as i32 + dx, y as i32 + dy)))
+        .any(|(x, y)| map.in_bounds((x, y)) && map.get_cell(x as usize, y as usize).is_walkable());
+
+      if next_to_floor {
+        map.set_cell(x, y, Cell::Wall);
+      }
+    }
+  }
+}
+
+fn floor_cells(map: &Map) -> Vec<(i32, i32)> {
+  (0..map.dims.1 as i32)
+    .flat_map(|y| (0..map.dims.0 as i32).map(move |x| (x, y)))
+    .filter(|&(x, y)| map.get_cell(x as usize, y as usize).is_walkable())
+    .collect()
+}
+
+fn random_floor_cells(map: &Map, rng: &mut StdRng, count: usize) -> Vec<(i32, i32)> {
+  let floor = floor_cells(map);
+  if floor.is_empty() { return vec![]; }
+
+  (0..count).map(|_| floor[rng.random_range(0..floor.len())]).collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rand::SeedableRng;
+
+  const KINDS: [GeneratorKind; 4] = [GeneratorKind::Rooms, GeneratorKind::Bsp, GeneratorKind::Caves, GeneratorKind::Walk];
+
+  #[test]
+  fn tiny_maps_dont_panic() {
+    for kind in KINDS {
+      for dims in [(0, 0), (1, 1), (2, 7), (7, 2), (3, 3), (4, 9), (5, 5), (11, 4), (20, 20)] {
+        for seed in 0..20 {
+          let GeneratedMap { map, spawns } = kind.generator().generate(dims, &mut StdRng::seed_from_u64(seed));
+
+          assert_eq!(map.dims, dims);
+          assert!(!spawns.is_empty(), "{:?} {:?}: no spawn", kind, dims);
+
+          if dims.0 >= 3 && dims.1 >= 3 {
+            let (x, y) = spawns[0];
+            assert!(map.get_cell(x as usize, y as usize).is_walkable(), "{:?} {:?} seed {}: spawn in rock", kind, dims, seed);
+          }
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn solid_caves_still_have_a_spawn() {
+    let caves = Caves { fill: 1.0, ..Default::default() };
+    let GeneratedMap { map, spawns } = caves.generate((30, 20), &mut StdRng::seed_from_u64(0));
+
+    assert_eq!(spawns[0], (15, 10));
+    assert!(map.get_cell(15, 10).is_walkable());
+    assert_eq!(map.get_cell(14, 9), Cell::Wall);
+  }
+}
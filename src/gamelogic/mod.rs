@@ -2,10 +2,13 @@
 pub mod macros;
 pub mod common_structs;
 pub mod gs_funcs;
+pub mod mapgen;
+pub mod connectivity;
 pub mod core;
 
 // pub use common_structs::{*};
 // pub use gs_funcs::{*};
 pub use core::{*};
+pub use mapgen::{GeneratorKind};
 
 
@@ -1,52 +1,80 @@
 use asciigame::{*};
 
-use winit::event_loop::EventLoop;
 use anyhow::Result;
 
 mod gamelogic;
 use gamelogic::{*};
 
-fn main() -> Result<()> {
-  
-  let args: Vec<String> = std::env::args().collect();
+/// Command line options.
+struct Config {
+  window: bool,
+  generator: GeneratorKind,
+  engine: EngineConfig,
+}
+
+impl Config {
+  fn from_args() -> Result<Self> {
+    let mut config = Config {
+      window: false,
+      generator: GeneratorKind::default(),
+      engine: EngineConfig::default(),
+    };
     
-  let c: char = if args.len() > 1 {
-    args[1].chars().next().unwrap().to_ascii_lowercase()
-  } else {
-    't'
-  };
-  
-  if c != 'w' {
-    let mut game = TerminalGame::<Walker>::new();
-    if let Ok(mut g) = game {
-      if let Err(e) = g.run() {
-        game = Err(e);
-      } else {
-        game = Ok(g);
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+      match arg.as_str() {
+        "-w" | "--window" | "w" | "window" => config.window = true,
+        "-t" | "--terminal" | "t" | "terminal" => config.window = false,
+        "-s" | "--seed" => {
+          let Some(value) = args.next() else { anyhow::bail!("'{}' needs a value", arg) };
+          config.engine.seed = Some(value.parse()?);
+        },
+        "-r" | "--record" => {
+          let Some(value) = args.next() else { anyhow::bail!("'{}' needs a value", arg) };
+          config.engine.record = Some(value.into());
+        },
+        "-p" | "--replay" => {
+          let Some(value) = args.next() else { anyhow::bail!("'{}' needs a value", arg) };
+          config.engine.replay = Some(value.into());
+        },
+        "-k" | "--keymap" => {
+          let Some(value) = args.next() else { anyhow::bail!("'{}' needs a value", arg) };
+          config.engine.keymap = Some(value.into());
+        },
+        "--release-timeout" => {
+          let Some(value) = args.next() else { anyhow::bail!("'{}' needs a value", arg) };
+          config.engine.release_timeout = Some(std::time::Duration::from_millis(value.parse()?));
+        },
+        "-g" | "--generator" => {
+          let Some(value) = args.next() else { anyhow::bail!("'{}' needs a value", arg) };
+          config.generator = value.parse()?;
+        },
+        _ => anyhow::bail!("unknown argument '{}'", arg),
       }
     }
     
-    // 4. Report
-    if let Err(e) = game {
-      eprintln!("Game Error: {:?}", e);
-      return Err(e);
-    }
+    Ok(config)
+  }
+}
+
+fn run(config: Config) -> Result<()> {
+  let init = |eng: &mut Engine<Walker>| Walker::with_generator(eng, config.generator);
+  
+  if config.window {
+    Game::with_init(WindowBackend::new()?, config.engine, init)?.run()
   } else {
-    let mut game = WindowGame::<Walker>::new();
-    if let Ok(mut g) = game {
-      let event_loop = EventLoop::new()?;
-      if let Err(e) = event_loop.run_app(&mut g) {
-        game = Err(e.into());
-      } else {
-        game = Ok(g);
-      }
-    }
-    
-    // 4. Report
-    if let Err(e) = game {
-      eprintln!("Game Error: {:?}", e);
-      return Err(e);
-    }
+    Game::with_init(TerminalBackend::new()?, config.engine, init)?.run()
+  }
+}
+
+fn main() -> Result<()> {
+  
+  let config = Config::from_args()?;
+  
+  // Report
+  if let Err(e) = run(config) {
+    eprintln!("Game Error: {:?}", e);
+    return Err(e);
   }
   
   Ok(())